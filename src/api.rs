@@ -0,0 +1,79 @@
+//! Trait over the client's core operations, for dependency injection and mocking
+
+use crate::client::QweryClient;
+use crate::error::Result;
+use crate::types::{HealthResponse, PaymentRequest, PaymentResponse, SettleRequest, SettleResponse, VerifyResponse};
+#[cfg(feature = "signing")]
+use solana_sdk::signature::Keypair;
+
+/// Core Qwery operations, implemented by [`QweryClient`]
+///
+/// Depend on this trait instead of the concrete client to swap in another
+/// implementation, such as a mock in unit tests.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use qwery_sdk::{QweryApi, QweryClient, Network};
+/// use std::sync::Arc;
+///
+/// struct AppState {
+///     payments: Arc<dyn QweryApi>,
+/// }
+///
+/// let state = AppState {
+///     payments: Arc::new(QweryClient::new(Network::Mainnet).unwrap()),
+/// };
+/// ```
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+pub trait QweryApi: Send + Sync {
+    /// See [`QweryClient::create_payment`]
+    async fn create_payment(&self, request: PaymentRequest) -> Result<PaymentResponse>;
+
+    /// See [`QweryClient::sign_and_settle`]
+    #[cfg(feature = "signing")]
+    async fn sign_and_settle(
+        &self,
+        payment: &PaymentResponse,
+        keypair: &Keypair,
+    ) -> Result<SettleResponse>;
+
+    /// See [`QweryClient::settle_payment`]
+    async fn settle_payment(&self, request: SettleRequest) -> Result<SettleResponse>;
+
+    /// See [`QweryClient::verify_payment`]
+    async fn verify_payment(&self, signature: &str) -> Result<VerifyResponse>;
+
+    /// See [`QweryClient::health`]
+    async fn health(&self) -> Result<HealthResponse>;
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl QweryApi for QweryClient {
+    async fn create_payment(&self, request: PaymentRequest) -> Result<PaymentResponse> {
+        QweryClient::create_payment(self, request).await
+    }
+
+    #[cfg(feature = "signing")]
+    async fn sign_and_settle(
+        &self,
+        payment: &PaymentResponse,
+        keypair: &Keypair,
+    ) -> Result<SettleResponse> {
+        QweryClient::sign_and_settle(self, payment, keypair).await
+    }
+
+    async fn settle_payment(&self, request: SettleRequest) -> Result<SettleResponse> {
+        QweryClient::settle_payment(self, request).await
+    }
+
+    async fn verify_payment(&self, signature: &str) -> Result<VerifyResponse> {
+        QweryClient::verify_payment(self, signature).await
+    }
+
+    async fn health(&self) -> Result<HealthResponse> {
+        QweryClient::health(self).await
+    }
+}
@@ -0,0 +1,167 @@
+//! Credentials sent with every facilitator request
+
+use crate::error::Result;
+use crate::runtime::Instant;
+use crate::types::SecretString;
+use std::fmt::Debug;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Supplies the bearer token sent with each request
+///
+/// Implement this for short-lived tokens from an auth service; a static
+/// [`QweryConfig::api_key`] is served by the [`SecretString`] implementation.
+/// Tokens are reused for [`token_lifetime`] and fetched again once 90% of it
+/// has passed, or straight away if the facilitator rejects one with
+/// `401 Unauthorized`. Set a provider via [`QweryConfig::token_provider`].
+///
+/// [`QweryConfig::api_key`]: crate::QweryConfig::api_key
+/// [`QweryConfig::token_provider`]: crate::QweryConfig::token_provider
+/// [`token_lifetime`]: TokenProvider::token_lifetime
+///
+/// # Example
+///
+/// ```rust
+/// use qwery_sdk::auth::TokenProvider;
+/// use qwery_sdk::error::Result;
+/// use qwery_sdk::QweryConfig;
+/// use std::sync::Arc;
+/// use std::time::Duration;
+///
+/// #[derive(Debug)]
+/// struct AuthService;
+///
+/// #[async_trait::async_trait]
+/// impl TokenProvider for AuthService {
+///     async fn token(&self) -> Result<String> {
+///         // Exchange client credentials with the auth service here
+///         Ok("short_lived_token".to_string())
+///     }
+///
+///     fn token_lifetime(&self) -> Option<Duration> {
+///         Some(Duration::from_secs(300))
+///     }
+/// }
+///
+/// let config = QweryConfig {
+///     token_provider: Some(Arc::new(AuthService)),
+///     ..Default::default()
+/// };
+/// ```
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+pub trait TokenProvider: Debug + Send + Sync {
+    /// Fetch a token to send as `Authorization: Bearer <token>`
+    async fn token(&self) -> Result<String>;
+
+    /// How long a token may be reused, or `None` to call [`token`] for every
+    /// request
+    ///
+    /// [`token`]: TokenProvider::token
+    fn token_lifetime(&self) -> Option<Duration> {
+        None
+    }
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl TokenProvider for SecretString {
+    async fn token(&self) -> Result<String> {
+        Ok(self.expose_secret().to_string())
+    }
+}
+
+/// A [`TokenProvider`] with the last token it returned
+#[derive(Debug)]
+pub(crate) struct CachedToken {
+    provider: Arc<dyn TokenProvider>,
+    cached: Mutex<Option<(Instant, SecretString)>>,
+}
+
+impl CachedToken {
+    pub(crate) fn new(provider: Arc<dyn TokenProvider>) -> Self {
+        Self {
+            provider,
+            cached: Mutex::new(None),
+        }
+    }
+
+    /// Return the cached token, fetching a new one if it is close to expiring
+    pub(crate) async fn get(&self) -> Result<SecretString> {
+        let refresh_after = self.provider.token_lifetime().map(|lifetime| lifetime.mul_f64(0.9));
+
+        if let Some(refresh_after) = refresh_after {
+            if let Some((fetched_at, ref token)) = *self.cached.lock().unwrap() {
+                if fetched_at.elapsed() < refresh_after {
+                    return Ok(token.clone());
+                }
+            }
+        }
+
+        let token = SecretString::from(self.provider.token().await?);
+        if refresh_after.is_some() {
+            *self.cached.lock().unwrap() = Some((Instant::now(), token.clone()));
+        }
+        Ok(token)
+    }
+
+    /// Drop the cached token so the next request fetches a fresh one
+    pub(crate) fn invalidate(&self) {
+        *self.cached.lock().unwrap() = None;
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Hands out `token-1`, `token-2`, ... with a fixed lifetime
+    #[derive(Debug)]
+    struct CountingProvider {
+        calls: AtomicUsize,
+        lifetime: Option<Duration>,
+    }
+
+    #[async_trait::async_trait]
+    impl TokenProvider for CountingProvider {
+        async fn token(&self) -> Result<String> {
+            Ok(format!("token-{}", self.calls.fetch_add(1, Ordering::SeqCst) + 1))
+        }
+
+        fn token_lifetime(&self) -> Option<Duration> {
+            self.lifetime
+        }
+    }
+
+    fn cached(lifetime: Option<Duration>) -> CachedToken {
+        CachedToken::new(Arc::new(CountingProvider {
+            calls: AtomicUsize::new(0),
+            lifetime,
+        }))
+    }
+
+    #[tokio::test]
+    async fn reuses_a_token_within_its_lifetime_until_invalidated() {
+        let token = cached(Some(Duration::from_secs(300)));
+
+        assert_eq!(token.get().await.unwrap().expose_secret(), "token-1");
+        assert_eq!(token.get().await.unwrap().expose_secret(), "token-1");
+
+        token.invalidate();
+        assert_eq!(token.get().await.unwrap().expose_secret(), "token-2");
+    }
+
+    #[tokio::test]
+    async fn fetches_every_time_without_a_lifetime_or_near_expiry() {
+        let token = cached(None);
+        assert_eq!(token.get().await.unwrap().expose_secret(), "token-1");
+        assert_eq!(token.get().await.unwrap().expose_secret(), "token-2");
+
+        // Refreshed once 90% of the lifetime has passed
+        let token = cached(Some(Duration::from_millis(20)));
+        assert_eq!(token.get().await.unwrap().expose_secret(), "token-1");
+        tokio::time::sleep(Duration::from_millis(19)).await;
+        assert_eq!(token.get().await.unwrap().expose_secret(), "token-2");
+    }
+}
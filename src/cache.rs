@@ -0,0 +1,112 @@
+//! Bounded least-recently-used cache of finalized verification results
+
+use crate::types::VerifyResponse;
+use std::collections::{HashMap, VecDeque};
+
+/// Verification results keyed by signature, evicting the least recently used
+#[derive(Debug, Default)]
+pub(crate) struct VerifyCache {
+    capacity: usize,
+    entries: HashMap<String, VerifyResponse>,
+    /// Signatures from least to most recently used
+    order: VecDeque<String>,
+}
+
+impl VerifyCache {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            ..Default::default()
+        }
+    }
+
+    pub(crate) fn get(&mut self, signature: &str) -> Option<VerifyResponse> {
+        let response = self.entries.get(signature)?.clone();
+        self.touch(signature);
+        Some(response)
+    }
+
+    pub(crate) fn insert(&mut self, signature: &str, response: VerifyResponse) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        if self.entries.insert(signature.to_string(), response).is_some() {
+            self.touch(signature);
+            return;
+        }
+
+        self.order.push_back(signature.to_string());
+        while self.order.len() > self.capacity {
+            if let Some(evicted) = self.order.pop_front() {
+                self.entries.remove(&evicted);
+            }
+        }
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.entries.clear();
+        self.order.clear();
+    }
+
+    /// Mark `signature` as the most recently used
+    fn touch(&mut self, signature: &str) {
+        if let Some(i) = self.order.iter().position(|s| s == signature) {
+            if let Some(s) = self.order.remove(i) {
+                self.order.push_back(s);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response(status: &str) -> VerifyResponse {
+        serde_json::from_value(serde_json::json!({
+            "verified": true,
+            "status": status,
+            "confirmations": 32,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn evicts_the_least_recently_used_signature() {
+        let mut cache = VerifyCache::new(2);
+        cache.insert("a", response("finalized"));
+        cache.insert("b", response("finalized"));
+
+        // Reading "a" makes "b" the oldest
+        assert!(cache.get("a").is_some());
+        cache.insert("c", response("finalized"));
+
+        assert!(cache.get("a").is_some());
+        assert!(cache.get("b").is_none());
+        assert!(cache.get("c").is_some());
+    }
+
+    #[test]
+    fn reinserting_replaces_without_evicting() {
+        let mut cache = VerifyCache::new(2);
+        cache.insert("a", response("confirmed"));
+        cache.insert("b", response("finalized"));
+        cache.insert("a", response("finalized"));
+
+        assert_eq!(cache.get("a").unwrap().status, "finalized");
+        assert!(cache.get("b").is_some());
+    }
+
+    #[test]
+    fn zero_capacity_and_clear_leave_nothing_cached() {
+        let mut cache = VerifyCache::new(0);
+        cache.insert("a", response("finalized"));
+        assert!(cache.get("a").is_none());
+
+        let mut cache = VerifyCache::new(4);
+        cache.insert("a", response("finalized"));
+        cache.clear();
+        assert!(cache.get("a").is_none());
+    }
+}
@@ -1,19 +1,113 @@
 //! Qwery API client
 
+use crate::auth::{CachedToken, TokenProvider};
+use crate::cache::VerifyCache;
+use crate::clock::Clock;
 use crate::error::{QweryError, Result};
+#[cfg(feature = "signing")]
+use crate::inspect::{ASSOCIATED_TOKEN_PROGRAM_ID, TOKEN_PROGRAM_ID};
+#[cfg(all(feature = "signing", not(target_arch = "wasm32")))]
+use crate::rpc::LazyRpcClient;
+use crate::runtime::{self, Instant};
+use crate::shutdown::Lifecycle;
+use crate::unsettled::UnsettledPayments;
+use crate::x402;
+#[cfg(feature = "signing")]
+use crate::signer::AsyncSigner;
 use crate::types::*;
-use reqwest::Client;
+use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION, CONTENT_TYPE, RETRY_AFTER};
+#[cfg(not(target_arch = "wasm32"))]
+use reqwest::header::ACCEPT;
+use reqwest::{Client, Method, RequestBuilder, StatusCode, Url};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use futures::future::{self, Either};
+use futures::stream::{self, StreamExt};
+#[cfg(not(target_arch = "wasm32"))]
+use bytes::Bytes;
+#[cfg(not(target_arch = "wasm32"))]
+use futures::Stream;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tokio_util::sync::CancellationToken;
+use tracing::Instrument;
+use uuid::Uuid;
 use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+#[cfg(feature = "signing")]
 use solana_sdk::{
-    signature::Keypair,
+    pubkey::Pubkey,
+    signature::{Keypair, Signature},
     transaction::Transaction,
 };
+use std::future::Future;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, UNIX_EPOCH};
+
+/// Facilitator API version this SDK is built against
+pub const API_VERSION: &str = "1.0";
+
+/// Header carrying [`API_VERSION`] on every request
+pub const API_VERSION_HEADER: &str = "X-Qwery-Api-Version";
+
+/// Header carrying the correlation ID of each request
+///
+/// Every request is sent with a fresh UUID in this header unless one is
+/// already set, e.g. through [`QweryClient::create_payment_with_headers`].
+/// The ID is recorded on the `qwery_request` tracing span and in
+/// [`QweryError::ApiError`] and [`QweryError::InvalidResponse`], so SDK logs
+/// can be joined with the facilitator's.
+pub const REQUEST_ID_HEADER: &str = "X-Request-Id";
+
+/// Header carrying the key for [`QweryClient::create_payment_idempotent`]
+const IDEMPOTENCY_KEY_HEADER: &str = "Idempotency-Key";
+
+/// Response header set to `true` when a create was answered from an earlier
+/// request with the same idempotency key
+const IDEMPOTENT_REPLAYED_HEADER: &str = "Idempotent-Replayed";
+
+/// User agent sent with every request
+#[cfg(not(target_arch = "wasm32"))]
+const USER_AGENT: &str = concat!("qwery-sdk-rust/", env!("CARGO_PKG_VERSION"));
+
+/// How long `preflight_health_check` reuses a health response
+const PREFLIGHT_HEALTH_TTL: Duration = Duration::from_secs(10);
+
+/// Prefix of the synthetic signatures returned when [`QweryConfig::dry_run`] is set
+///
+/// ```rust
+/// use qwery_sdk::{SettleResponse, DRY_RUN_SIGNATURE_PREFIX};
+///
+/// fn was_dry_run(response: &SettleResponse) -> bool {
+///     response
+///         .signature
+///         .as_deref()
+///         .is_some_and(|signature| signature.starts_with(DRY_RUN_SIGNATURE_PREFIX))
+/// }
+/// ```
+pub const DRY_RUN_SIGNATURE_PREFIX: &str = "dry-run-";
 
 /// Main client for interacting with the Qwery API
 #[derive(Debug, Clone)]
 pub struct QweryClient {
-    config: QweryConfig,
+    /// Shared so that cloning the client doesn't copy the configuration
+    config: Arc<QweryConfig>,
     http_client: Client,
+    /// `facilitator_url` plus `api_base_path`, with a trailing slash for joining
+    base_url: Url,
+    /// Last health response and when it was fetched, per `config.clock`
+    health_cache: Arc<Mutex<Option<(Duration, HealthResponse)>>>,
+    verify_cache: Arc<Mutex<VerifyCache>>,
+    /// Bearer token source, from `token_provider` or else `api_key`
+    auth: Option<Arc<CachedToken>>,
+    lifecycle: Arc<Lifecycle>,
+    /// Slots for `max_concurrent_requests`, if set
+    request_slots: Option<Arc<Semaphore>>,
+    #[cfg(all(feature = "signing", not(target_arch = "wasm32")))]
+    rpc: Arc<LazyRpcClient>,
+    /// Payments awaiting settlement, if `warn_unsettled_on_drop` is set
+    unsettled: Option<Arc<UnsettledPayments>>,
+    /// Deadline for every operation, set with `with_deadline`
+    deadline: Option<Instant>,
 }
 
 impl QweryClient {
@@ -34,8 +128,59 @@ impl QweryClient {
         Self::with_config(config)
     }
 
+    /// Create a client and check it against the live facilitator straight away
+    ///
+    /// Unlike [`with_config`](QweryClient::with_config), which never touches
+    /// the network, this fetches the facilitator's health with the configured
+    /// credentials, so a wrong URL, rejected API key or incompatible API
+    /// version fails here rather than on the first payment. Errors are those
+    /// of [`check_compatibility`](QweryClient::check_compatibility).
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use qwery_sdk::{QweryClient, QweryConfig};
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let client = QweryClient::connect(QweryConfig {
+    ///         api_key: Some("your_api_key".into()),
+    ///         ..Default::default()
+    ///     }).await?;
+    ///     println!("Connected to {}", client.config().facilitator_url);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn connect(config: QweryConfig) -> Result<Self> {
+        let client = Self::with_config(config)?;
+        client.check_compatibility().await?;
+        Ok(client)
+    }
+
+    /// Start building a client, overriding only the settings you need
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use qwery_sdk::{QweryClient, Network};
+    /// use std::time::Duration;
+    ///
+    /// let client = QweryClient::builder()
+    ///     .network(Network::Devnet)
+    ///     .api_key("your_api_key")
+    ///     .timeout(Duration::from_secs(10))
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn builder() -> QweryClientBuilder {
+        QweryClientBuilder::default()
+    }
+
     /// Create a new Qwery client with custom configuration
     ///
+    /// Returns [`QweryError::ConfigError`] if `facilitator_url` is not a
+    /// valid `http` or `https` URL. A trailing slash is removed.
+    ///
     /// # Example
     ///
     /// ```rust,no_run
@@ -44,24 +189,78 @@ impl QweryClient {
     /// let config = QweryConfig {
     ///     facilitator_url: "https://facilitator.qwery.xyz".to_string(),
     ///     network: Network::Devnet,
-    ///     api_key: Some("your_api_key".to_string()),
+    ///     api_key: Some("your_api_key".into()),
+    ///     ..Default::default()
     /// };
     /// let client = QweryClient::with_config(config).unwrap();
     /// ```
-    pub fn with_config(config: QweryConfig) -> Result<Self> {
-        let http_client = Client::builder()
-            .timeout(std::time::Duration::from_secs(30))
-            .build()
-            .map_err(QweryError::RequestError)?;
+    pub fn with_config(mut config: QweryConfig) -> Result<Self> {
+        config.facilitator_url = validate_facilitator_url(&config.facilitator_url)?;
+        check_reserved_headers(&config.default_headers)?;
+        if config.max_concurrent_requests == Some(0) {
+            return Err(QweryError::ConfigError(
+                "max_concurrent_requests must be at least 1".to_string(),
+            ));
+        }
+        let base_url = match config.api_base_path {
+            Some(ref base_path) => format!("{}/{}/", config.facilitator_url, base_path.trim_matches('/')),
+            None => format!("{}/", config.facilitator_url),
+        };
+        let base_url = Url::parse(&base_url).map_err(|e| {
+            QweryError::ConfigError(format!("invalid api_base_path {:?}: {}", config.api_base_path, e))
+        })?;
+
+        // The browser fetch backend manages its own timeouts and connection pool
+        #[cfg(not(target_arch = "wasm32"))]
+        let builder = {
+            let user_agent = match config.user_agent_suffix {
+                Some(ref suffix) => format!("{} {}", USER_AGENT, suffix),
+                None => USER_AGENT.to_string(),
+            };
+
+            Client::builder()
+                .user_agent(user_agent)
+                .timeout(config.timeout)
+                .connect_timeout(config.connect_timeout)
+                .pool_max_idle_per_host(config.pool_max_idle_per_host)
+                .pool_idle_timeout(config.pool_idle_timeout)
+        };
+        #[cfg(target_arch = "wasm32")]
+        let builder = Client::builder();
+
+        let http_client = builder.build().map_err(QweryError::RequestError)?;
+        let auth = match (&config.token_provider, &config.api_key) {
+            (Some(provider), _) => Some(provider.clone()),
+            (None, Some(api_key)) => Some(Arc::new(api_key.clone()) as Arc<dyn TokenProvider>),
+            (None, None) => None,
+        };
 
         Ok(Self {
-            config,
             http_client,
+            base_url,
+            health_cache: Arc::new(Mutex::new(None)),
+            verify_cache: Arc::new(Mutex::new(VerifyCache::new(config.verify_cache_size))),
+            auth: auth.map(|provider| Arc::new(CachedToken::new(provider))),
+            lifecycle: Arc::new(Lifecycle::default()),
+            request_slots: config.max_concurrent_requests.map(|n| Arc::new(Semaphore::new(n))),
+            #[cfg(all(feature = "signing", not(target_arch = "wasm32")))]
+            rpc: Arc::default(),
+            unsettled: config.warn_unsettled_on_drop.then(Arc::default),
+            deadline: None,
+            config: Arc::new(config),
         })
     }
 
     /// Create a new payment request
     ///
+    /// For USDC/USDT payments the facilitator includes an idempotent
+    /// create-ATA instruction, so recipients without a token account yet can
+    /// still be paid.
+    ///
+    /// An amount with more decimal places than the token supports, such as
+    /// 0.1234567 USDC, fails with [`QweryError::ConfigError`] unless
+    /// [`QweryConfig::rounding_policy`] allows rounding it.
+    ///
     /// # Example
     ///
     /// ```rust,no_run
@@ -76,6 +275,7 @@ impl QweryClient {
     ///         token: "SOL".to_string(),
     ///         recipient: "recipient_address".to_string(),
     ///         metadata: None,
+    ///         ..Default::default()
     ///     }).await?;
     ///
     ///     println!("Payment ID: {}", payment.payment_id);
@@ -83,143 +283,3198 @@ impl QweryClient {
     /// }
     /// ```
     pub async fn create_payment(&self, request: PaymentRequest) -> Result<PaymentResponse> {
-        let url = format!("{}/payments/create", self.config.facilitator_url);
+        self.create_payment_with_headers(request, HeaderMap::new()).await
+    }
 
-        let mut req = self.http_client.post(&url).json(&serde_json::json!({
-            "amount": request.amount,
-            "token": request.token,
-            "recipient": request.recipient,
-            "network": self.config.network.as_str(),
-            "metadata": request.metadata,
-        }));
+    /// Create a payment, adding `headers` to this request only
+    ///
+    /// The headers are applied on top of [`QweryConfig::default_headers`].
+    /// Reserved headers are rejected with [`QweryError::ConfigError`]. Set
+    /// [`REQUEST_ID_HEADER`] here to use your own correlation ID instead of
+    /// a generated one.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use qwery_sdk::{QweryClient, PaymentRequest, Network};
+    /// use reqwest::header::{HeaderMap, HeaderValue};
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let client = QweryClient::new(Network::Mainnet)?;
+    ///
+    ///     let mut headers = HeaderMap::new();
+    ///     headers.insert("x-trace-id", HeaderValue::from_static("abc123"));
+    ///
+    ///     let payment = client.create_payment_with_headers(PaymentRequest {
+    ///         amount: 0.01,
+    ///         token: "SOL".to_string(),
+    ///         recipient: "recipient_address".to_string(),
+    ///         ..Default::default()
+    ///     }, headers).await?;
+    ///
+    ///     println!("Payment ID: {}", payment.payment_id);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn create_payment_with_headers(
+        &self,
+        request: PaymentRequest,
+        headers: HeaderMap,
+    ) -> Result<PaymentResponse> {
+        let result = self
+            .submit_payment(request, headers)
+            .await
+            .map(|(payment, _)| payment);
+        self.record_created(result.as_ref());
+        result
+    }
 
-        if let Some(ref api_key) = self.config.api_key {
-            req = req.header("Authorization", format!("Bearer {}", api_key));
-        }
+    /// Create a payment at most once per `idempotency_key`
+    ///
+    /// The key is sent as the `Idempotency-Key` header. If the facilitator
+    /// already created a payment for it, that payment is returned as
+    /// [`CreateOutcome::Existing`] rather than [`CreateOutcome::Created`], so
+    /// callers can tell a replayed create from a new one. Only new payments
+    /// are reported to [`QweryConfig::metrics`].
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use qwery_sdk::{CreateOutcome, QweryClient, PaymentRequest, Network};
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let client = QweryClient::new(Network::Mainnet)?;
+    ///
+    ///     let request = PaymentRequest {
+    ///         amount: 5.0,
+    ///         token: "USDC".to_string(),
+    ///         recipient: "recipient_address".to_string(),
+    ///         ..Default::default()
+    ///     };
+    ///     match client.create_payment_idempotent(request, "order-1042").await? {
+    ///         CreateOutcome::Created(payment) => println!("Billing {}", payment.payment_id),
+    ///         CreateOutcome::Existing(payment) => println!("Already billed {}", payment.payment_id),
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn create_payment_idempotent(
+        &self,
+        request: PaymentRequest,
+        idempotency_key: &str,
+    ) -> Result<CreateOutcome> {
+        let key = HeaderValue::from_str(idempotency_key)
+            .map_err(|e| QweryError::ConfigError(format!("invalid idempotency key: {}", e)))?;
+        let mut headers = HeaderMap::new();
+        headers.insert(IDEMPOTENCY_KEY_HEADER, key);
 
-        let response = req.send().await?;
+        let (payment, response_headers) = match self.submit_payment(request, headers).await {
+            Ok(created) => created,
+            Err(e) => {
+                self.record_created(Err(&e));
+                return Err(e);
+            }
+        };
 
-        if !response.status().is_success() {
-            let error_text = response.text().await.unwrap_or_default();
-            return Err(QweryError::ApiError(error_text));
+        let replayed = response_headers
+            .get(IDEMPOTENT_REPLAYED_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| value.eq_ignore_ascii_case("true"));
+        if replayed {
+            if let Some(ref unsettled) = self.unsettled {
+                unsettled.created(&payment.payment_id);
+            }
+            return Ok(CreateOutcome::Existing(payment));
         }
 
-        let payment: PaymentResponse = response.json().await?;
-        Ok(payment)
+        self.record_created(Ok(&payment));
+        Ok(CreateOutcome::Created(payment))
     }
 
-    /// Sign and settle a payment using a keypair
+    /// Create the payment for `order_key`, or return it if it was already created
+    ///
+    /// Shorthand for [`create_payment_idempotent`] with the order key as the
+    /// idempotency key, for callers that don't need to know whether the
+    /// payment is new. A retried checkout for the same order gets the same
+    /// payment back instead of a duplicate.
     ///
     /// # Example
     ///
     /// ```rust,no_run
     /// use qwery_sdk::{QweryClient, PaymentRequest, Network};
-    /// use solana_sdk::signature::Keypair;
     ///
     /// #[tokio::main]
     /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    ///     let client = QweryClient::new(Network::Devnet)?;
-    ///     let keypair = Keypair::new();
+    ///     let client = QweryClient::new(Network::Mainnet)?;
     ///
-    ///     let payment = client.create_payment(PaymentRequest {
-    ///         amount: 0.01,
-    ///         token: "SOL".to_string(),
+    ///     let request = PaymentRequest {
+    ///         amount: 5.0,
+    ///         token: "USDC".to_string(),
     ///         recipient: "recipient_address".to_string(),
-    ///         metadata: None,
-    ///     }).await?;
+    ///         ..Default::default()
+    ///     };
+    ///     let payment = client.create_or_get_payment("order-1042", request).await?;
+    ///     println!("Checkout for order-1042: {}", payment.payment_id);
+    ///     Ok(())
+    /// }
+    /// ```
     ///
-    ///     let result = client.sign_and_settle(&payment, &keypair).await?;
-    ///     println!("Signature: {:?}", result.signature);
+    /// [`create_payment_idempotent`]: QweryClient::create_payment_idempotent
+    pub async fn create_or_get_payment(
+        &self,
+        order_key: &str,
+        request: PaymentRequest,
+    ) -> Result<PaymentResponse> {
+        self.create_payment_idempotent(request, order_key)
+            .await
+            .map(CreateOutcome::into_payment)
+    }
+
+    /// Validate and send a create request, returning the response headers too
+    async fn submit_payment(
+        &self,
+        mut request: PaymentRequest,
+        headers: HeaderMap,
+    ) -> Result<(PaymentResponse, HeaderMap)> {
+        let _in_flight = self.lifecycle.enter()?;
+        check_reserved_headers(&headers)?;
+        self.check_payment(&mut request).await?;
+        self.check_health().await?;
+
+        if self.config.balance_preflight {
+            if let Some(ref payer) = request.payer {
+                #[cfg(feature = "signing")]
+                Pubkey::from_str(payer)
+                    .map_err(|e| QweryError::ConfigError(format!("invalid payer: {}", e)))?;
+                let token = Token::from_str(&request.token)?;
+                let required = token.to_base_units(request.amount);
+                let available = self.fetch_balance(payer, token).await?;
+                if available < required {
+                    return Err(QweryError::InsufficientFunds {
+                        token,
+                        required,
+                        available: Some(available),
+                    });
+                }
+            }
+        }
+
+        let req = self
+            .request(Method::POST, "/payments/create")?
+            .headers(headers)
+            .json(&self.payment_body(&request));
+
+        self.send_with_headers(req, "/payments/create", None).await
+    }
+
+    /// Create one payment that pays several recipients atomically
+    ///
+    /// The facilitator builds a single transaction with one transfer per
+    /// split, so it is signed, settled and verified like any other payment
+    /// under one `payment_id`.
+    ///
+    /// Every split must have a recipient and a positive amount, and is then
+    /// checked and rounded like a [`create_payment`] request of its own. The
+    /// amount cap also applies to the total.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use qwery_sdk::{QweryClient, Network, PaymentSplit, Token};
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let client = QweryClient::new(Network::Mainnet)?;
+    ///
+    ///     let payment = client.create_split_payment(vec![
+    ///         PaymentSplit::new("seller_wallet", 9.5),
+    ///         PaymentSplit::new("marketplace_wallet", 0.5),
+    ///     ], Token::Usdc).await?;
+    ///
+    ///     println!("Payment ID: {}", payment.payment_id);
     ///     Ok(())
     /// }
     /// ```
-    pub async fn sign_and_settle(
+    ///
+    /// [`create_payment`]: QweryClient::create_payment
+    pub async fn create_split_payment(
         &self,
-        payment: &PaymentResponse,
-        keypair: &Keypair,
-    ) -> Result<SettleResponse> {
-        // Decode the transaction
-        let tx_bytes = BASE64.decode(&payment.transaction)
-            .map_err(QweryError::Base64Error)?;
+        mut splits: Vec<PaymentSplit>,
+        token: Token,
+    ) -> Result<PaymentResponse> {
+        let _in_flight = self.lifecycle.enter()?;
+        if splits.is_empty() {
+            return Err(QweryError::ConfigError(
+                "split payment needs at least one recipient".to_string(),
+            ));
+        }
+        for split in &mut splits {
+            if split.recipient.is_empty() {
+                return Err(QweryError::ConfigError("split recipient is empty".to_string()));
+            }
+            if !(split.amount.is_finite() && split.amount > 0.0) {
+                return Err(QweryError::ConfigError(format!(
+                    "split amount for {} must be positive, got {}",
+                    split.recipient, split.amount
+                )));
+            }
 
-        let mut transaction: Transaction = bincode::deserialize(&tx_bytes)
-            .map_err(|e| QweryError::SolanaError(e.to_string()))?;
+            // Each leg is a transfer of its own, held to the same limits
+            let mut leg = PaymentRequest {
+                amount: split.amount,
+                token: token.as_str().to_string(),
+                recipient: split.recipient.clone(),
+                ..Default::default()
+            };
+            self.check_payment(&mut leg).await?;
+            split.amount = leg.amount;
+        }
 
-        // Sign the transaction
-        transaction.partial_sign(&[keypair], transaction.message.recent_blockhash);
+        let amount: f64 = splits.iter().map(|split| split.amount).sum();
+        self.check_amount_cap(token.as_str(), amount)?;
+        self.check_health().await?;
 
-        // Encode the signed transaction
-        let signed_bytes = bincode::serialize(&transaction)
-            .map_err(|e| QweryError::SolanaError(e.to_string()))?;
-        let signed_base64 = BASE64.encode(&signed_bytes);
+        let req = self.request(Method::POST, "/payments/create")?.json(&CreateSplitPaymentBody {
+            amount,
+            token: token.as_str(),
+            splits: &splits,
+            network: self.config.network.as_str(),
+        });
 
-        // Settle the payment
-        self.settle_payment(SettleRequest {
-            payment_id: payment.payment_id.clone(),
-            signed_transaction: signed_base64,
-        }).await
+        let result = self.send(req, "/payments/create", None).await;
+        self.record_created(result.as_ref());
+        result
     }
 
-    /// Settle a payment with a pre-signed transaction
-    pub async fn settle_payment(&self, request: SettleRequest) -> Result<SettleResponse> {
-        let url = format!("{}/payments/settle", self.config.facilitator_url);
+    /// Estimate the network fees the facilitator will pay for a payment
+    ///
+    /// No payment is created.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use qwery_sdk::{QweryClient, PaymentRequest, Network};
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let client = QweryClient::new(Network::Mainnet)?;
+    ///
+    ///     let estimate = client.estimate_fee(&PaymentRequest {
+    ///         amount: 0.01,
+    ///         token: "SOL".to_string(),
+    ///         recipient: "recipient_address".to_string(),
+    ///         ..Default::default()
+    ///     }).await?;
+    ///
+    ///     println!("Base fee: {} lamports", estimate.base_fee_lamports);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn estimate_fee(&self, request: &PaymentRequest) -> Result<FeeEstimate> {
+        request.validate()?;
 
-        let mut req = self.http_client.post(&url).json(&request);
+        let req = self.request(Method::POST, "/payments/estimate")?.json(&self.payment_body(request));
 
-        if let Some(ref api_key) = self.config.api_key {
-            req = req.header("Authorization", format!("Bearer {}", api_key));
-        }
+        self.send(req, "/payments/estimate", None).await
+    }
+
+    /// Get current slot time, confirmation latency and a suggested priority
+    /// fee for the configured network
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use qwery_sdk::{QweryClient, Network, PaymentRequest};
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let client = QweryClient::new(Network::Mainnet)?;
+    ///
+    ///     let conditions = client.network_conditions().await?;
+    ///     println!("Expect finality in ~{:?}", conditions.estimated_time_to_finality());
+    ///
+    ///     let request = PaymentRequest::builder()
+    ///         .amount(1.0)
+    ///         .token("USDC")
+    ///         .recipient("recipient_address")
+    ///         .priority_fee_micro_lamports(conditions.suggested_priority_fee_micro_lamports)
+    ///         .build()?;
+    ///     client.create_payment(request).await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn network_conditions(&self) -> Result<NetworkConditions> {
+        let req = self
+            .request(Method::GET, "/network/conditions")?
+            .query(&[("network", self.config.network.as_str())]);
 
-        let response = req.send().await?;
+        self.send(req, "/network/conditions", None).await
+    }
 
-        if !response.status().is_success() {
-            let error_text = response.text().await.unwrap_or_default();
-            return Err(QweryError::ApiError(error_text));
-        }
+    /// Check that the facilitator's fee payer can pay for settlements on the
+    /// configured network
+    ///
+    /// Settlements fail once the fee payer runs dry, so high-value
+    /// integrations can poll this and alert ahead of time.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use qwery_sdk::{QweryClient, Network};
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let client = QweryClient::new(Network::Mainnet)?;
+    ///
+    ///     let status = client.facilitator_status().await?;
+    ///     if status.is_low(1_000) {
+    ///         eprintln!(
+    ///             "Fee payer low: {:?} lamports, ~{:?} settlements left",
+    ///             status.balance_lamports, status.estimated_transactions_remaining
+    ///         );
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn facilitator_status(&self) -> Result<FacilitatorStatus> {
+        let req = self
+            .request(Method::GET, "/facilitator/status")?
+            .query(&[("network", self.config.network.as_str())]);
 
-        let settle_response: SettleResponse = response.json().await?;
-        Ok(settle_response)
+        self.send(req, "/facilitator/status", None).await
     }
 
-    /// Verify a payment by transaction signature
-    pub async fn verify_payment(&self, signature: &str) -> Result<VerifyResponse> {
-        let url = format!("{}/payments/verify", self.config.facilitator_url);
+    /// Fetch the current state of a payment by its ID
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use qwery_sdk::{QweryClient, Network};
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let client = QweryClient::new(Network::Mainnet)?;
+    ///
+    ///     let payment = client.get_payment("payment_id").await?;
+    ///     println!("Status: {}", payment.status);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn get_payment(&self, payment_id: &str) -> Result<PaymentResponse> {
+        let path = format!("/payments/{}", path_segment(payment_id)?);
+        let req = self.request(Method::GET, &path)?;
 
-        let response = self.http_client
-            .post(&url)
-            .json(&VerifyRequest {
-                signature: signature.to_string(),
-                network: self.config.network.as_str().to_string(),
-            })
-            .send()
-            .await?;
+        self.send(req, &path, Some(payment_id)).await
+    }
 
-        if !response.status().is_success() {
-            let error_text = response.text().await.unwrap_or_default();
-            return Err(QweryError::ApiError(error_text));
-        }
+    /// Fetch one page of payments matching `filter`
+    ///
+    /// Pass the returned `next_cursor` back as `filter.cursor` to fetch the
+    /// following page.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use qwery_sdk::{QweryClient, Network, PaymentFilter, PaymentStatus};
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let client = QweryClient::new(Network::Mainnet)?;
+    ///
+    ///     let mut filter = PaymentFilter {
+    ///         status: Some(PaymentStatus::Settled),
+    ///         ..Default::default()
+    ///     };
+    ///     loop {
+    ///         let page = client.list_payments(&filter).await?;
+    ///         for payment in &page.payments {
+    ///             println!("{} {} {}", payment.payment_id, payment.amount, payment.token);
+    ///         }
+    ///         match page.next_cursor {
+    ///             Some(cursor) => filter.cursor = Some(cursor),
+    ///             None => break,
+    ///         }
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn list_payments(&self, filter: &PaymentFilter) -> Result<PaymentPage> {
+        let req = self
+            .request(Method::GET, "/payments")?
+            .query(&[("network", self.config.network.as_str())])
+            .query(filter);
 
-        let verify_response: VerifyResponse = response.json().await?;
-        Ok(verify_response)
+        self.send(req, "/payments", None).await
     }
 
-    /// Check the health of the facilitator
-    pub async fn health(&self) -> Result<HealthResponse> {
-        let url = format!("{}/health", self.config.facilitator_url);
+    /// Fetch every payment matching `filter`, following cursors to the last page
+    ///
+    /// Returns [`QweryError::TooManyResults`] rather than a truncated list
+    /// if more than [`QweryConfig::max_list_results`] payments match.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use qwery_sdk::{QweryClient, Network, PaymentFilter};
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let client = QweryClient::new(Network::Mainnet)?;
+    ///
+    ///     let payments = client.list_all_payments(PaymentFilter::default()).await?;
+    ///     println!("{} payments", payments.len());
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn list_all_payments(&self, mut filter: PaymentFilter) -> Result<Vec<PaymentResponse>> {
+        let limit = self.config.max_list_results;
+        let mut payments = Vec::new();
 
-        let response = self.http_client.get(&url).send().await?;
+        loop {
+            let page = self.list_payments(&filter).await?;
+            payments.extend(page.payments);
+            if payments.len() > limit {
+                return Err(QweryError::TooManyResults { limit });
+            }
 
-        if !response.status().is_success() {
-            let error_text = response.text().await.unwrap_or_default();
-            return Err(QweryError::ApiError(error_text));
+            match page.next_cursor {
+                Some(cursor) => filter.cursor = Some(cursor),
+                None => return Ok(payments),
+            }
         }
+    }
 
-        let health: HealthResponse = response.json().await?;
-        Ok(health)
+    /// Download every payment matching `filter` as CSV or NDJSON, chunk by chunk
+    ///
+    /// The export is streamed as the facilitator sends it rather than
+    /// buffered, so even very large exports can be written straight to a
+    /// file. The `limit` and `cursor` of `filter` are ignored by the export.
+    /// [`QweryConfig::timeout`] covers the whole download, so raise it for
+    /// large exports. Not available on `wasm32`, where response bodies can
+    /// only be read whole.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use futures::StreamExt;
+    /// use qwery_sdk::{ExportFormat, QweryClient, Network, PaymentFilter};
+    /// use std::io::Write;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let client = QweryClient::new(Network::Mainnet)?;
+    ///     let mut file = std::fs::File::create("payments.csv")?;
+    ///
+    ///     let export = client.export_payments(PaymentFilter::default(), ExportFormat::Csv).await?;
+    ///     let mut export = std::pin::pin!(export);
+    ///     while let Some(chunk) = export.next().await {
+    ///         file.write_all(&chunk?)?;
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    #[cfg(not(target_arch = "wasm32"))]
+    pub async fn export_payments(
+        &self,
+        filter: PaymentFilter,
+        format: ExportFormat,
+    ) -> Result<impl Stream<Item = Result<Bytes>>> {
+        let req = self
+            .request(Method::GET, "/payments/export")?
+            .header(ACCEPT, format.content_type())
+            .query(&[("network", self.config.network.as_str()), ("format", format.as_str())])
+            .query(&filter);
+        let (req, request_id) = with_request_id(req);
+        let span = tracing::debug_span!(
+            "qwery_request",
+            endpoint = "/payments/export",
+            request_id = %request_id,
+        );
+
+        let (response, permit) = self
+            .send_attempts(req, "/payments/export", None, &request_id)
+            .instrument(span)
+            .await?;
+        // Hold the request slot until the export has been read
+        Ok(response.bytes_stream().map(move |chunk| {
+            let _ = &permit;
+            chunk.map_err(QweryError::from)
+        }))
+    }
+
+    /// Get the balance of a wallet in base units (lamports for SOL)
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use qwery_sdk::{QweryClient, Network, Token};
+    /// use solana_sdk::pubkey::Pubkey;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let client = QweryClient::new(Network::Mainnet)?;
+    ///
+    ///     let balance = client.get_balance(&Pubkey::new_unique(), Token::Usdc).await?;
+    ///     println!("Balance: {}", balance);
+    ///     Ok(())
+    /// }
+    /// ```
+    #[cfg(feature = "signing")]
+    pub async fn get_balance(&self, address: &Pubkey, token: Token) -> Result<u64> {
+        self.fetch_balance(&address.to_string(), token).await
+    }
+
+    /// Get the balance of a wallet address in base units (lamports for SOL)
+    ///
+    /// Without the `signing` feature there is no `Pubkey` type, so the
+    /// address is passed as a base58 string.
+    #[cfg(not(feature = "signing"))]
+    pub async fn get_balance(&self, address: &str, token: Token) -> Result<u64> {
+        self.fetch_balance(address, token).await
+    }
+
+    /// Fetch a wallet balance by its base58 address
+    async fn fetch_balance(&self, address: &str, token: Token) -> Result<u64> {
+        let path = format!("/balances/{}", path_segment(address)?);
+        let req = self.request(Method::GET, &path)?.query(&[
+            ("token", token.as_str()),
+            ("network", self.config.network.as_str()),
+        ]);
+
+        let balance: BalanceResponse = self.send(req, &path, None).await?;
+        Ok(balance.balance)
+    }
+
+    /// Resolve the associated token account that receives `token` for `owner`
+    ///
+    /// Returns [`QweryError::ConfigError`] for SOL, which is paid directly to
+    /// the wallet, and for tokens without a mint on the configured network.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use qwery_sdk::{QweryClient, Network, Token};
+    /// use solana_sdk::pubkey::Pubkey;
+    ///
+    /// let client = QweryClient::new(Network::Mainnet).unwrap();
+    /// let ata = client.resolve_recipient_ata(&Pubkey::new_unique(), Token::Usdc).unwrap();
+    /// println!("USDC account: {}", ata);
+    /// ```
+    #[cfg(feature = "signing")]
+    pub fn resolve_recipient_ata(&self, owner: &Pubkey, token: Token) -> Result<Pubkey> {
+        let mint = token.mint_address(self.config.network).ok_or_else(|| {
+            QweryError::ConfigError(format!(
+                "{} has no token account on {}",
+                token,
+                self.config.network.as_str()
+            ))
+        })?;
+
+        let (ata, _) = Pubkey::find_program_address(
+            &[owner.as_ref(), TOKEN_PROGRAM_ID.as_ref(), mint.as_ref()],
+            &ASSOCIATED_TOKEN_PROGRAM_ID,
+        );
+        Ok(ata)
+    }
+
+    /// Refund a settled payment, fully or partially
+    ///
+    /// `amount` is in base units; `None` refunds the full amount. Returns the
+    /// resulting refund payment record, or [`QweryError::InvalidPaymentState`]
+    /// if the payment hasn't been settled.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use qwery_sdk::{QweryClient, Network};
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let client = QweryClient::new(Network::Mainnet)?;
+    ///
+    ///     // Refund 0.50 USDC of the original payment
+    ///     let refund = client.refund_payment("payment_id", Some(500_000)).await?;
+    ///     println!("Refund ID: {}", refund.payment_id);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn refund_payment(
+        &self,
+        payment_id: &str,
+        amount: Option<u64>,
+    ) -> Result<PaymentResponse> {
+        let payment = self.get_payment(payment_id).await?;
+        if payment.payment_status() != PaymentStatus::Settled {
+            return Err(QweryError::InvalidPaymentState {
+                payment_id: payment_id.to_string(),
+                action: "refunded",
+                status: payment.status,
+            });
+        }
+
+        let path = format!("/payments/{}/refund", path_segment(payment_id)?);
+        let req = self.request(Method::POST, &path)?.json(&serde_json::json!({
+            "amount": amount,
+            "network": self.config.network.as_str(),
+        }));
+
+        self.send(req, &path, Some(payment_id)).await
+    }
+
+    /// Create many payments with up to `concurrency` requests in flight
+    ///
+    /// Results are returned in the same order as `requests`, and each one is
+    /// independent so a single failure doesn't abort the rest of the batch.
+    /// The returned [`BatchOutcome`] summarizes which requests failed.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use qwery_sdk::{QweryClient, PaymentRequest, Network};
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let client = QweryClient::new(Network::Mainnet)?;
+    ///
+    ///     let requests = vec![
+    ///         PaymentRequest {
+    ///             amount: 1.0,
+    ///             token: "USDC".to_string(),
+    ///             recipient: "first_recipient".to_string(),
+    ///             ..Default::default()
+    ///         },
+    ///         PaymentRequest {
+    ///             amount: 2.0,
+    ///             token: "USDC".to_string(),
+    ///             recipient: "second_recipient".to_string(),
+    ///             ..Default::default()
+    ///         },
+    ///     ];
+    ///
+    ///     let outcome = client.create_payments(requests, 8).await;
+    ///     println!("{} of {} payments created", outcome.success_count(), outcome.len());
+    ///     for (index, e) in outcome.failed() {
+    ///         eprintln!("Request {} failed: {}", index, e);
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn create_payments(
+        &self,
+        requests: Vec<PaymentRequest>,
+        concurrency: usize,
+    ) -> BatchOutcome<PaymentResponse> {
+        self.create_payments_with_progress(requests, concurrency, |_, _| {}).await
+    }
+
+    /// Like [`create_payments`], calling `progress` with each request's index
+    /// and result as soon as it completes
+    ///
+    /// Completion order may differ from input order; the returned results
+    /// are still in the same order as `requests`.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use qwery_sdk::{QweryClient, PaymentRequest, Network};
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let client = QweryClient::new(Network::Mainnet)?;
+    ///     let requests: Vec<PaymentRequest> = Vec::new();
+    ///     let total = requests.len();
+    ///
+    ///     let mut done = 0;
+    ///     client
+    ///         .create_payments_with_progress(requests, 8, |index, result| {
+    ///             done += 1;
+    ///             println!("[{}/{}] request {} ok: {}", done, total, index, result.is_ok());
+    ///         })
+    ///         .await;
+    ///     Ok(())
+    /// }
+    /// ```
+    ///
+    /// [`create_payments`]: QweryClient::create_payments
+    pub async fn create_payments_with_progress(
+        &self,
+        requests: Vec<PaymentRequest>,
+        concurrency: usize,
+        progress: impl FnMut(usize, &Result<PaymentResponse>),
+    ) -> BatchOutcome<PaymentResponse> {
+        run_batch(requests, concurrency, |request| self.create_payment(request), progress)
+            .await
+            .into()
+    }
+
+    /// Sign and settle a payment using a keypair
+    ///
+    /// Returns [`QweryError::SettlementFailed`] if the facilitator reports
+    /// that settlement did not succeed, and [`QweryError::NetworkMismatch`]
+    /// without contacting the facilitator if the payment was created on a
+    /// different network than this client is configured for.
+    ///
+    /// Signatures already on the transaction, such as the facilitator's as
+    /// fee payer, are kept. If any required signer other than the fee payer,
+    /// whose slot the facilitator may fill at settlement, still lacks a valid
+    /// signature after signing, or a signature present is invalid,
+    /// [`QweryError::IncompleteSignatures`] names them and nothing is
+    /// submitted. Likewise a signed transaction over
+    /// [`MAX_TRANSACTION_SIZE`] bytes, say from many splits or a long memo,
+    /// fails with [`QweryError::TransactionTooLarge`].
+    ///
+    /// If the blockhash expires before the transaction lands, settlement
+    /// fails with [`QweryError::BlockhashExpired`]. With
+    /// [`QweryConfig::refresh_blockhash`] the payment is instead fetched
+    /// again and, once the facilitator has rebuilt it on a fresh blockhash,
+    /// signed and resubmitted, up to [`QweryConfig::max_blockhash_retries`]
+    /// times.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use qwery_sdk::{QweryClient, PaymentRequest, Network};
+    /// use solana_sdk::signature::Keypair;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let client = QweryClient::new(Network::Devnet)?;
+    ///     let keypair = Keypair::new();
+    ///
+    ///     let payment = client.create_payment(PaymentRequest {
+    ///         amount: 0.01,
+    ///         token: "SOL".to_string(),
+    ///         recipient: "recipient_address".to_string(),
+    ///         metadata: None,
+    ///         ..Default::default()
+    ///     }).await?;
+    ///
+    ///     let result = client.sign_and_settle(&payment, &keypair).await?;
+    ///     println!("Signature: {:?}", result.signature);
+    ///     Ok(())
+    /// }
+    /// ```
+    #[cfg(feature = "signing")]
+    pub async fn sign_and_settle(
+        &self,
+        payment: &PaymentResponse,
+        keypair: &Keypair,
+    ) -> Result<SettleResponse> {
+        let signed = self.sign_payment(payment, keypair).await?;
+        self.settle_signed(payment, &[keypair], signed).await
+    }
+
+    /// Sign a payment's transaction with a keypair without settling it
+    ///
+    /// Runs the same checks and signing as [`sign_and_settle`] and returns
+    /// the signed transaction instead of submitting it, e.g. to store it for
+    /// auditing or submit it elsewhere. Settle it later by passing it to
+    /// [`settle_payment`].
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use qwery_sdk::{QweryClient, PaymentResponse};
+    /// use solana_sdk::signature::Keypair;
+    ///
+    /// async fn sign(
+    ///     client: &QweryClient,
+    ///     payment: &PaymentResponse,
+    ///     keypair: &Keypair,
+    /// ) -> qwery_sdk::error::Result<()> {
+    ///     let signed = client.sign_payment(payment, keypair).await?;
+    ///     println!("Signed {} as {}", signed.payment_id, signed.signature);
+    ///
+    ///     let result = client.settle_payment(signed.into()).await?;
+    ///     println!("Status: {}", result.status);
+    ///     Ok(())
+    /// }
+    /// ```
+    ///
+    /// [`sign_and_settle`]: QweryClient::sign_and_settle
+    /// [`settle_payment`]: QweryClient::settle_payment
+    #[cfg(feature = "signing")]
+    pub async fn sign_payment(
+        &self,
+        payment: &PaymentResponse,
+        keypair: &Keypair,
+    ) -> Result<SignedPayment> {
+        self.sign_payment_async(payment, &[keypair]).await
+    }
+
+    /// Sign a payment's transaction with each of `signers` in turn without
+    /// settling it
+    #[cfg(feature = "signing")]
+    async fn sign_payment_async<S: AsyncSigner + ?Sized>(
+        &self,
+        payment: &PaymentResponse,
+        signers: &[&S],
+    ) -> Result<SignedPayment> {
+        self.check_network(&payment.network)?;
+        self.check_amount_cap(&payment.token, payment.amount)?;
+
+        // Decode the transaction
+        #[allow(unused_mut)]
+        let mut transaction = payment.decode_transaction()?;
+
+        #[cfg(not(target_arch = "wasm32"))]
+        if self.config.refresh_blockhash {
+            self.refresh_blockhash(&mut transaction).await?;
+        }
+
+        // Sign the transaction
+        for signer in signers {
+            transaction = signer.sign_transaction(transaction).await?;
+        }
+        check_signatures(&transaction)?;
+
+        signed_payment(payment, &transaction)
+    }
+
+    /// Settle a signed payment, signing it again if the facilitator reports
+    /// that its blockhash expired
+    ///
+    /// The transaction is rebuilt by the facilitator rather than patched
+    /// locally, so its own fee payer signature stays valid. Re-signing needs
+    /// `refresh_blockhash` and is attempted up to `max_blockhash_retries`
+    /// times; if it isn't possible the [`QweryError::BlockhashExpired`] is
+    /// returned as is.
+    #[cfg(feature = "signing")]
+    async fn settle_signed<S: AsyncSigner + ?Sized>(
+        &self,
+        payment: &PaymentResponse,
+        signers: &[&S],
+        signed: SignedPayment,
+    ) -> Result<SettleResponse> {
+        let mut result = self.settle_payment(signed.into()).await;
+        if !self.config.refresh_blockhash {
+            return result;
+        }
+
+        let mut current = payment.clone();
+        let mut attempt = 0;
+        while matches!(result, Err(QweryError::BlockhashExpired { .. }))
+            && attempt < self.config.max_blockhash_retries
+        {
+            attempt += 1;
+            tracing::debug!(payment_id = %payment.payment_id, attempt, "re-signing after blockhash expired");
+            match self.rebuilt_payment(&current, signers).await {
+                Ok((rebuilt, signed)) => {
+                    current = rebuilt;
+                    result = self.settle_payment(signed.into()).await;
+                }
+                Err(e) => {
+                    tracing::debug!(error = %e, "could not re-sign after blockhash expired");
+                    break;
+                }
+            }
+        }
+
+        result
+    }
+
+    /// Fetch a payment whose blockhash expired again, and sign the
+    /// transaction the facilitator rebuilt for it
+    #[cfg(feature = "signing")]
+    async fn rebuilt_payment<S: AsyncSigner + ?Sized>(
+        &self,
+        payment: &PaymentResponse,
+        signers: &[&S],
+    ) -> Result<(PaymentResponse, SignedPayment)> {
+        let stale = payment.decode_transaction()?.message.recent_blockhash;
+        let rebuilt = self.get_payment(&payment.payment_id).await?;
+        if rebuilt.decode_transaction()?.message.recent_blockhash == stale {
+            return Err(QweryError::SigningError(
+                "facilitator has not rebuilt the transaction on a fresh blockhash".to_string(),
+            ));
+        }
+
+        let signed = self.sign_payment_async(&rebuilt, signers).await?;
+        Ok((rebuilt, signed))
+    }
+
+    /// Sign and settle a payment with an [`AsyncSigner`]
+    ///
+    /// Use this when signing happens outside the process, e.g. in a browser
+    /// wallet extension.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use qwery_sdk::{QweryClient, PaymentResponse};
+    /// use qwery_sdk::signer::AsyncSigner;
+    ///
+    /// async fn pay(
+    ///     client: &QweryClient,
+    ///     payment: &PaymentResponse,
+    ///     wallet: &(dyn AsyncSigner + Sync),
+    /// ) -> qwery_sdk::error::Result<()> {
+    ///     let result = client.sign_and_settle_async(payment, wallet).await?;
+    ///     println!("Signature: {:?}", result.signature);
+    ///     Ok(())
+    /// }
+    /// ```
+    #[cfg(feature = "signing")]
+    pub async fn sign_and_settle_async<S: AsyncSigner + ?Sized>(
+        &self,
+        payment: &PaymentResponse,
+        signer: &S,
+    ) -> Result<SettleResponse> {
+        let signed = self.sign_payment_async(payment, &[signer]).await?;
+        self.settle_signed(payment, &[signer], signed).await
+    }
+
+    /// Sign and settle a payment that needs several signers of different kinds
+    ///
+    /// Each of `signers` signs in order, e.g. a hardware wallet, a local
+    /// [`Keypair`] and a remote signing service for a multisig transaction.
+    /// If any required signature is still missing afterwards,
+    /// [`QweryError::IncompleteSignatures`] names the signers and nothing is
+    /// submitted. Otherwise this behaves like [`sign_and_settle`].
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use qwery_sdk::{QweryClient, PaymentResponse};
+    /// use qwery_sdk::signer::AsyncSigner;
+    /// use solana_sdk::signature::Keypair;
+    ///
+    /// async fn approve(
+    ///     client: &QweryClient,
+    ///     payment: &PaymentResponse,
+    ///     treasurer: &Keypair,
+    ///     custody: &dyn AsyncSigner,
+    /// ) -> qwery_sdk::error::Result<()> {
+    ///     let result = client.sign_and_settle_multi(payment, &[treasurer, custody]).await?;
+    ///     println!("Signature: {:?}", result.signature);
+    ///     Ok(())
+    /// }
+    /// ```
+    ///
+    /// [`sign_and_settle`]: QweryClient::sign_and_settle
+    #[cfg(feature = "signing")]
+    pub async fn sign_and_settle_multi(
+        &self,
+        payment: &PaymentResponse,
+        signers: &[&dyn AsyncSigner],
+    ) -> Result<SettleResponse> {
+        if signers.is_empty() {
+            return Err(QweryError::SigningError("no signers given".to_string()));
+        }
+
+        let signed = self.sign_payment_async(payment, signers).await?;
+        self.settle_signed(payment, signers, signed).await
+    }
+
+    /// Sign and settle a payment, returning [`QweryError::Cancelled`] as soon as
+    /// `cancel` is triggered
+    #[cfg(feature = "signing")]
+    pub async fn sign_and_settle_cancellable(
+        &self,
+        payment: &PaymentResponse,
+        keypair: &Keypair,
+        cancel: &CancellationToken,
+    ) -> Result<SettleResponse> {
+        cancellable(cancel, self.sign_and_settle(payment, keypair)).await
+    }
+
+    /// Fetch a stored payment by ID, then sign and settle it
+    ///
+    /// Use this to resume a payment after a restart when only the
+    /// `payment_id` was kept. Returns [`QweryError::InvalidPaymentState`] if
+    /// the payment is no longer pending, e.g. because it expired or was
+    /// already settled.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use qwery_sdk::{QweryClient, Network};
+    /// use qwery_sdk::signer::load_keypair_file;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let client = QweryClient::new(Network::Devnet)?;
+    ///     let keypair = load_keypair_file("id.json")?;
+    ///
+    ///     let result = client.sign_and_settle_by_id("payment_id", &keypair).await?;
+    ///     println!("Signature: {:?}", result.signature);
+    ///     Ok(())
+    /// }
+    /// ```
+    #[cfg(feature = "signing")]
+    pub async fn sign_and_settle_by_id(
+        &self,
+        payment_id: &str,
+        keypair: &Keypair,
+    ) -> Result<SettleResponse> {
+        let payment = self.get_payment(payment_id).await?;
+        if payment.payment_status() != PaymentStatus::Pending {
+            return Err(QweryError::InvalidPaymentState {
+                payment_id: payment_id.to_string(),
+                action: "signed",
+                status: payment.status,
+            });
+        }
+
+        self.sign_and_settle(&payment, keypair).await
+    }
+
+    /// Sign and settle a payment whose fees are paid by a separate account
+    ///
+    /// Use this for payments created with [`PaymentRequest::fee_payer`], where
+    /// both the payer and the fee payer must sign.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use qwery_sdk::{QweryClient, PaymentRequest, Network};
+    /// use solana_sdk::signature::{Keypair, Signer};
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let client = QweryClient::new(Network::Devnet)?;
+    ///     let user = Keypair::new();
+    ///     let sponsor = Keypair::new();
+    ///
+    ///     let request = PaymentRequest::builder()
+    ///         .amount(0.01)
+    ///         .token("SOL")
+    ///         .recipient("recipient_address")
+    ///         .payer(user.pubkey().to_string())
+    ///         .fee_payer(sponsor.pubkey())
+    ///         .build()?;
+    ///
+    ///     let payment = client.create_payment(request).await?;
+    ///     let result = client.sign_and_settle_with(&payment, &user, &sponsor).await?;
+    ///     println!("Signature: {:?}", result.signature);
+    ///     Ok(())
+    /// }
+    /// ```
+    #[cfg(feature = "signing")]
+    pub async fn sign_and_settle_with(
+        &self,
+        payment: &PaymentResponse,
+        keypair: &Keypair,
+        fee_payer: &Keypair,
+    ) -> Result<SettleResponse> {
+        let signers = [keypair, fee_payer];
+        let signed = self.sign_payment_async(payment, &signers).await?;
+        self.settle_signed(payment, &signers, signed).await
+    }
+
+    /// Settle a payment with a pre-signed transaction
+    ///
+    /// A response with `success == false` is returned as
+    /// [`QweryError::SettlementFailed`]; use [`settle_payment_raw`] to get the
+    /// response unchanged. The error carries the transaction's program logs,
+    /// as returned by the facilitator or, failing that, fetched from
+    /// `rpc_url` when one is configured.
+    ///
+    /// If the transaction failed because the payer could not cover the
+    /// transfer, the payment is looked up and [`QweryError::InsufficientFunds`]
+    /// is returned with the amount it required.
+    ///
+    /// [`settle_payment_raw`]: QweryClient::settle_payment_raw
+    pub async fn settle_payment(&self, request: SettleRequest) -> Result<SettleResponse> {
+        self.settle(request, None).await
+    }
+
+    /// Settle a payment, controlling how the facilitator submits the transaction
+    ///
+    /// Behaves like [`settle_payment`], with `options` passed through to the
+    /// facilitator's `sendTransaction` call.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use qwery_sdk::{QweryClient, Network, SettleOptions, SettleRequest};
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let client = QweryClient::new(Network::Mainnet)?;
+    ///
+    ///     let request = SettleRequest {
+    ///         payment_id: "payment_id".to_string(),
+    ///         signed_transaction: "base64_signed_tx".to_string(),
+    ///     };
+    ///     let options = SettleOptions { skip_preflight: true, ..Default::default() };
+    ///     let result = client.settle_payment_with_options(request, options).await?;
+    ///     println!("Signature: {:?}", result.signature);
+    ///     Ok(())
+    /// }
+    /// ```
+    ///
+    /// [`settle_payment`]: QweryClient::settle_payment
+    pub async fn settle_payment_with_options(
+        &self,
+        request: SettleRequest,
+        options: SettleOptions,
+    ) -> Result<SettleResponse> {
+        self.settle(request, Some(options)).await
+    }
+
+    async fn settle(
+        &self,
+        request: SettleRequest,
+        options: Option<SettleOptions>,
+    ) -> Result<SettleResponse> {
+        let started = Instant::now();
+        let payment_id = request.payment_id.clone();
+
+        let result = self.settle_raw(request, options).await;
+
+        if let Some(ref metrics) = self.config.metrics {
+            let success = result.as_ref().is_ok_and(|r| r.success);
+            metrics.on_settlement(&payment_id, success, started.elapsed());
+        }
+
+        let error = match result {
+            Ok(response) if response.success => {
+                if let Some(ref unsettled) = self.unsettled {
+                    unsettled.settled(&payment_id);
+                }
+                return Ok(response);
+            }
+            #[allow(unused_mut)]
+            Ok(mut response) => {
+                #[cfg(all(feature = "signing", not(target_arch = "wasm32")))]
+                if response.logs.is_empty() {
+                    if let Some(ref signature) = response.signature {
+                        response.logs = self.transaction_logs(signature).await;
+                    }
+                }
+                settlement_error(&payment_id, response)
+            }
+            Err(e) => e,
+        };
+
+        let insufficient_funds = match error {
+            QweryError::SettlementFailed { ref error, ref logs, .. } => error
+                .iter()
+                .chain(logs)
+                .any(|message| is_insufficient_funds(message)),
+            QweryError::ApiError { ref message, .. } => is_insufficient_funds(message),
+            _ => false,
+        };
+        if insufficient_funds {
+            // The failure doesn't say how much was needed, the payment does
+            if let Ok(payment) = self.get_payment(&payment_id).await {
+                if let Ok(token) = Token::from_str(&payment.token) {
+                    return Err(QweryError::InsufficientFunds {
+                        token,
+                        required: token.to_base_units(payment.amount),
+                        available: None,
+                    });
+                }
+            }
+        }
+
+        Err(error)
+    }
+
+    /// Settle a payment with a signed transaction in its serialized wire format
+    ///
+    /// For transactions signed elsewhere, such as by an external signing
+    /// service, this saves encoding them to base64 for [`settle_payment`].
+    /// Errors are the same as for [`settle_payment`], except that a
+    /// transaction over [`MAX_TRANSACTION_SIZE`] bytes fails with
+    /// [`QweryError::TransactionTooLarge`] without being submitted.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use qwery_sdk::{QweryClient, Network};
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let client = QweryClient::new(Network::Mainnet)?;
+    ///
+    ///     let signed_tx: Vec<u8> = std::fs::read("signed_tx.bin")?;
+    ///     let result = client.settle_signed_bytes("payment_id", &signed_tx).await?;
+    ///     println!("Signature: {:?}", result.signature);
+    ///     Ok(())
+    /// }
+    /// ```
+    ///
+    /// [`settle_payment`]: QweryClient::settle_payment
+    pub async fn settle_signed_bytes(
+        &self,
+        payment_id: &str,
+        signed_tx: &[u8],
+    ) -> Result<SettleResponse> {
+        if signed_tx.len() > MAX_TRANSACTION_SIZE {
+            return Err(QweryError::TransactionTooLarge { size: signed_tx.len() });
+        }
+
+        self.settle_payment(SettleRequest {
+            payment_id: payment_id.to_string(),
+            signed_transaction: BASE64.encode(signed_tx),
+        }).await
+    }
+
+    /// Settle a payment, returning the facilitator's response even if settlement failed
+    ///
+    /// If the connection fails or times out the request may still have been
+    /// processed, so before retrying (per [`QweryConfig::retry_policy`]) the
+    /// payment is fetched: a payment that is already settled is returned as
+    /// a success without resubmitting, taking its signature from
+    /// [`settlement_status`] when available, and the signed transaction is
+    /// only sent again while the payment is still pending.
+    ///
+    /// With [`QweryConfig::dry_run`] the signed transaction is only decoded
+    /// locally and a successful response is returned whose signature starts
+    /// with [`DRY_RUN_SIGNATURE_PREFIX`].
+    ///
+    /// [`settlement_status`]: QweryClient::settlement_status
+    pub async fn settle_payment_raw(&self, request: SettleRequest) -> Result<SettleResponse> {
+        self.settle_raw(request, None).await
+    }
+
+    async fn settle_raw(
+        &self,
+        request: SettleRequest,
+        options: Option<SettleOptions>,
+    ) -> Result<SettleResponse> {
+        let _in_flight = self.lifecycle.enter()?;
+        let started = Instant::now();
+
+        if self.config.dry_run {
+            let tx_bytes = decode_base64(&request.signed_transaction)?;
+            #[cfg(feature = "signing")]
+            bincode::deserialize::<Transaction>(&tx_bytes)
+                .map_err(|e| QweryError::SolanaError(e.to_string()))?;
+            #[cfg(not(feature = "signing"))]
+            let _ = tx_bytes;
+
+            return Ok(SettleResponse {
+                success: true,
+                signature: Some(format!("{}{}", DRY_RUN_SIGNATURE_PREFIX, request.payment_id)),
+                status: "settled".to_string(),
+                error: None,
+                logs: Vec::new(),
+                submit_latency: Some(started.elapsed()),
+            });
+        }
+
+        let body = SettleBody { request: &request, options };
+        let policy = &self.config.retry_policy;
+        let mut attempt = 0;
+
+        'submit: loop {
+            let req = self.request(Method::POST, "/payments/settle")?.json(&body);
+            let result: Result<SettleResponse> =
+                self.send(req, "/payments/settle", Some(&request.payment_id)).await;
+
+            let error = match result {
+                Ok(mut response) => {
+                    response.submit_latency = Some(started.elapsed());
+                    return Ok(response);
+                }
+                Err(e) if is_transport_error(&e) => e,
+                Err(e) => return Err(e),
+            };
+
+            // The request may have reached the facilitator before the connection
+            // failed, so check the payment before submitting it again
+            loop {
+                if !policy.should_retry(attempt, started.elapsed(), policy.backoff(attempt)) {
+                    return Err(error);
+                }
+                self.before_deadline(runtime::sleep(policy.backoff(attempt))).await?;
+                attempt += 1;
+
+                let Ok(payment) = self.get_payment(&request.payment_id).await else {
+                    continue;
+                };
+                match payment.payment_status() {
+                    PaymentStatus::Pending => continue 'submit,
+                    PaymentStatus::Submitted => continue,
+                    PaymentStatus::Settled => {
+                        let mut response = self
+                            .settlement_status(&request.payment_id)
+                            .await
+                            .unwrap_or(SettleResponse {
+                                success: true,
+                                signature: None,
+                                status: payment.status,
+                                error: None,
+                                logs: Vec::new(),
+                                submit_latency: None,
+                            });
+                        response.submit_latency = Some(started.elapsed());
+                        return Ok(response);
+                    }
+                    _ => {
+                        return Ok(SettleResponse {
+                            success: false,
+                            signature: None,
+                            error: Some(format!(
+                                "payment is {} after retrying settlement",
+                                payment.status
+                            )),
+                            logs: Vec::new(),
+                            status: payment.status,
+                            submit_latency: Some(started.elapsed()),
+                        })
+                    }
+                }
+            }
+        }
+    }
+
+    /// Fetch the latest settlement state of a payment without submitting anything
+    ///
+    /// Unlike [`verify_payment`], which checks the chain by signature, this
+    /// asks the facilitator how far settlement of `payment_id` has got. The
+    /// signature is filled in once the transaction has been sent.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use qwery_sdk::{QweryClient, Network};
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let client = QweryClient::new(Network::Mainnet)?;
+    ///
+    ///     let settlement = client.settlement_status("payment_id").await?;
+    ///     println!("Status: {} ({:?})", settlement.status, settlement.signature);
+    ///     Ok(())
+    /// }
+    /// ```
+    ///
+    /// [`verify_payment`]: QweryClient::verify_payment
+    pub async fn settlement_status(&self, payment_id: &str) -> Result<SettleResponse> {
+        let path = format!("/payments/{}/settlement", path_segment(payment_id)?);
+        let req = self.request(Method::GET, &path)?;
+
+        self.send(req, &path, Some(payment_id)).await
+    }
+
+    /// Settle many pre-signed payments with up to `concurrency` requests in flight
+    ///
+    /// Results are returned in the same order as `requests`, and each one is
+    /// independent so a single failure doesn't abort the rest of the batch.
+    /// The returned [`BatchOutcome`] summarizes which settlements failed.
+    pub async fn settle_payments(
+        &self,
+        requests: Vec<SettleRequest>,
+        concurrency: usize,
+    ) -> BatchOutcome<SettleResponse> {
+        self.settle_payments_with_progress(requests, concurrency, |_, _| {}).await
+    }
+
+    /// Like [`settle_payments`], calling `progress` with each request's index
+    /// and result as soon as it completes
+    ///
+    /// [`settle_payments`]: QweryClient::settle_payments
+    pub async fn settle_payments_with_progress(
+        &self,
+        requests: Vec<SettleRequest>,
+        concurrency: usize,
+        progress: impl FnMut(usize, &Result<SettleResponse>),
+    ) -> BatchOutcome<SettleResponse> {
+        run_batch(requests, concurrency, |request| self.settle_payment(request), progress)
+            .await
+            .into()
+    }
+
+    /// Verify a payment by transaction signature
+    ///
+    /// With the `signing` feature the signature is parsed locally first, so a
+    /// malformed signature fails with [`QweryError::ConfigError`] without a
+    /// round trip.
+    ///
+    /// Finalized results are cached when [`QweryConfig::verify_cache_size`]
+    /// is non-zero, so checking the same signature again doesn't reach the
+    /// facilitator.
+    ///
+    /// If the facilitator reports verifying on a different network than this
+    /// client is configured for, the result fails with
+    /// [`QweryError::NetworkMismatch`] instead of being returned.
+    pub async fn verify_payment(&self, signature: &str) -> Result<VerifyResponse> {
+        #[cfg(feature = "signing")]
+        Signature::from_str(signature)
+            .map_err(|e| QweryError::ConfigError(format!("invalid signature: {}", e)))?;
+
+        self.verify_request(signature, None).await
+    }
+
+    /// Verify a payment, only treating it as verified once it reaches `commitment`
+    ///
+    /// Without a commitment the facilitator applies its own default.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use qwery_sdk::{CommitmentLevel, QweryClient, Network};
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let client = QweryClient::new(Network::Mainnet)?;
+    ///
+    ///     let result = client
+    ///         .verify_payment_with_commitment("transaction_signature", CommitmentLevel::Finalized)
+    ///         .await?;
+    ///     println!("Verified: {} at {:?}", result.verified, result.commitment);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn verify_payment_with_commitment(
+        &self,
+        signature: &str,
+        commitment: CommitmentLevel,
+    ) -> Result<VerifyResponse> {
+        #[cfg(feature = "signing")]
+        Signature::from_str(signature)
+            .map_err(|e| QweryError::ConfigError(format!("invalid signature: {}", e)))?;
+
+        self.verify_request(signature, Some(commitment)).await
+    }
+
+    /// Verify many payments with up to `concurrency` requests in flight
+    ///
+    /// Results are returned in the same order as `signatures`, and each one
+    /// is verified and retried independently, so a single failure doesn't
+    /// abort the rest of the batch.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use qwery_sdk::{QweryClient, Network};
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let client = QweryClient::new(Network::Mainnet)?;
+    ///
+    ///     let signatures = vec!["signature_1".to_string(), "signature_2".to_string()];
+    ///     let results = client.verify_payments(&signatures, 8).await;
+    ///     for (signature, result) in signatures.iter().zip(results) {
+    ///         match result {
+    ///             Ok(response) => println!("{}: verified {}", signature, response.verified),
+    ///             Err(e) => println!("{}: {}", signature, e),
+    ///         }
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn verify_payments(
+        &self,
+        signatures: &[String],
+        concurrency: usize,
+    ) -> Vec<Result<VerifyResponse>> {
+        run_batch(
+            signatures.iter().collect(),
+            concurrency,
+            |signature| self.verify_payment(signature),
+            |_, _| {},
+        )
+        .await
+    }
+
+    /// Like [`verify_payments`], only treating each payment as verified once
+    /// it reaches `commitment`
+    ///
+    /// [`verify_payments`]: QweryClient::verify_payments
+    pub async fn verify_payments_with_commitment(
+        &self,
+        signatures: &[String],
+        commitment: CommitmentLevel,
+        concurrency: usize,
+    ) -> Vec<Result<VerifyResponse>> {
+        run_batch(
+            signatures.iter().collect(),
+            concurrency,
+            |signature| self.verify_payment_with_commitment(signature, commitment),
+            |_, _| {},
+        )
+        .await
+    }
+
+    /// Verify a payment by an already parsed transaction signature
+    #[cfg(feature = "signing")]
+    pub async fn verify_payment_sig(&self, signature: &Signature) -> Result<VerifyResponse> {
+        self.verify_request(&signature.to_string(), None).await
+    }
+
+    /// Send a verify request for a signature without parsing it
+    async fn verify_request(
+        &self,
+        signature: &str,
+        commitment: Option<CommitmentLevel>,
+    ) -> Result<VerifyResponse> {
+        if let Some(cached) = self.verify_cache.lock().unwrap().get(signature) {
+            return Ok(cached);
+        }
+
+        let req = self.request(Method::POST, "/payments/verify")?.json(&VerifyRequest {
+            signature: signature.to_string(),
+            network: self.config.network.as_str().to_string(),
+            commitment,
+        });
+        let response: VerifyResponse = self.send(req, "/payments/verify", None).await?;
+        if let Some(ref network) = response.network {
+            self.check_network(network)?;
+        }
+
+        if response.is_finalized() {
+            self.verify_cache
+                .lock()
+                .unwrap()
+                .insert(signature, response.clone());
+        }
+
+        Ok(response)
+    }
+
+    /// Forget every verification result cached under [`QweryConfig::verify_cache_size`]
+    pub fn clear_cache(&self) {
+        self.verify_cache.lock().unwrap().clear();
+    }
+
+    /// Poll `verify_payment` until the transaction is verified or `timeout` elapses
+    ///
+    /// The poll interval backs off according to [`QweryConfig::poll`]. A
+    /// transaction that will never verify, because it failed or doesn't match
+    /// the payment, ends the wait straight away with
+    /// [`QweryError::TransactionFailed`]. On a handle from
+    /// [`with_deadline`](Self::with_deadline), the deadline also ends the wait.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use qwery_sdk::{QweryClient, Network};
+    /// use std::time::Duration;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let client = QweryClient::new(Network::Mainnet)?;
+    ///
+    ///     let result = client
+    ///         .wait_for_confirmation("transaction_signature", Duration::from_secs(30))
+    ///         .await?;
+    ///     println!("Confirmations: {:?}", result.confirmations);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn wait_for_confirmation(
+        &self,
+        signature: &str,
+        timeout: Duration,
+    ) -> Result<VerifyResponse> {
+        let clock = &self.config.clock;
+        let started = clock.monotonic();
+        let mut attempt = 0;
+
+        loop {
+            let mut response = self.verify_payment(signature).await?;
+            let elapsed = clock.monotonic().saturating_sub(started);
+            match response.verify_status() {
+                VerifyStatus::Confirmed => {
+                    response.time_to_finality = Some(elapsed);
+                    return Ok(response);
+                }
+                VerifyStatus::Failed => {
+                    return Err(QweryError::TransactionFailed {
+                        signature: signature.to_string(),
+                        status: response.status,
+                    });
+                }
+                VerifyStatus::NotFound | VerifyStatus::Pending => {}
+            }
+
+            if elapsed >= timeout {
+                return Err(QweryError::ConfirmationTimeout {
+                    signature: signature.to_string(),
+                    elapsed,
+                });
+            }
+
+            let interval = self.config.poll.interval(attempt).min(timeout - elapsed);
+            self.before_deadline(runtime::sleep(interval)).await?;
+            attempt += 1;
+        }
+    }
+
+    /// Like [`wait_for_confirmation`], but returns [`QweryError::Cancelled`] as
+    /// soon as `cancel` is triggered
+    ///
+    /// [`wait_for_confirmation`]: QweryClient::wait_for_confirmation
+    pub async fn wait_for_confirmation_cancellable(
+        &self,
+        signature: &str,
+        timeout: Duration,
+        cancel: &CancellationToken,
+    ) -> Result<VerifyResponse> {
+        cancellable(cancel, self.wait_for_confirmation(signature, timeout)).await
+    }
+
+    /// Check the health of the facilitator
+    pub async fn health(&self) -> Result<HealthResponse> {
+        let req = self.request(Method::GET, "/health")?;
+
+        self.send(req, "/health", None).await
+    }
+
+    /// Ask the facilitator which network it serves
+    ///
+    /// Reads the networks reported by [`health`](QweryClient::health),
+    /// ignoring any this SDK doesn't know. Returns
+    /// [`QweryError::ConfigError`] if there isn't exactly one, since the
+    /// network then has to be chosen explicitly.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use qwery_sdk::{QweryClient, Network};
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let client = QweryClient::new(Network::Mainnet)?;
+    ///
+    ///     if client.detect_network().await? != client.config().network {
+    ///         eprintln!("Facilitator serves a different network");
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn detect_network(&self) -> Result<Network> {
+        let health = self.health().await?;
+
+        let mut served: Vec<Network> =
+            health.networks.keys().filter_map(|name| Network::from_wire(name)).collect();
+        served.sort_by_key(Network::as_str);
+        match served[..] {
+            [network] => Ok(network),
+            [] => Err(QweryError::ConfigError(
+                "facilitator reports no known network, set the network explicitly".to_string(),
+            )),
+            _ => Err(QweryError::ConfigError(format!(
+                "facilitator serves several networks ({}), set the network explicitly",
+                served.iter().map(Network::as_str).collect::<Vec<_>>().join(", ")
+            ))),
+        }
+    }
+
+    /// Check the health of the facilitator and measure the round trip
+    ///
+    /// Requests are bounded by [`QweryConfig::timeout`]; build a dedicated
+    /// client with a short timeout to use this as a latency probe.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use qwery_sdk::QweryClient;
+    /// use std::time::Duration;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let probe = QweryClient::builder()
+    ///         .timeout(Duration::from_secs(2))
+    ///         .build()?;
+    ///
+    ///     let (health, latency) = probe.health_with_latency().await?;
+    ///     println!("{} in {:?}", health.status, latency);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn health_with_latency(&self) -> Result<(HealthResponse, Duration)> {
+        let started = Instant::now();
+        let health = self.health().await?;
+        Ok((health, started.elapsed()))
+    }
+
+    /// Check that the facilitator speaks the API version this SDK was built for
+    ///
+    /// Compares the major version reported by [`health`](QweryClient::health)
+    /// with [`API_VERSION`] and returns [`QweryError::IncompatibleApiVersion`]
+    /// if they differ or the reported version can't be parsed.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use qwery_sdk::{QweryClient, Network};
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let client = QweryClient::new(Network::Mainnet)?;
+    ///     client.check_compatibility().await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn check_compatibility(&self) -> Result<()> {
+        let health = self.health().await?;
+
+        let major = |version: &str| {
+            version
+                .trim_start_matches(['v', 'V'])
+                .split('.')
+                .next()
+                .and_then(|major| major.parse::<u64>().ok())
+        };
+
+        match major(&health.version) {
+            Some(actual) if Some(actual) == major(API_VERSION) => Ok(()),
+            _ => Err(QweryError::IncompatibleApiVersion {
+                expected: API_VERSION.to_string(),
+                actual: health.version,
+            }),
+        }
+    }
+
+    /// Check the health of the facilitator, reusing a result younger than `ttl`
+    ///
+    /// The cache is shared between clones of this client.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use qwery_sdk::{QweryClient, Network};
+    /// use std::time::Duration;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let client = QweryClient::new(Network::Mainnet)?;
+    ///
+    ///     let health = client.health_cached(Duration::from_secs(10)).await?;
+    ///     println!("Ready: {}", health.is_healthy());
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn health_cached(&self, ttl: Duration) -> Result<HealthResponse> {
+        let clock = &self.config.clock;
+        if let Some((fetched_at, ref health)) = *self.health_cache.lock().unwrap() {
+            if clock.monotonic().saturating_sub(fetched_at) < ttl {
+                return Ok(health.clone());
+            }
+        }
+
+        let health = self.health().await?;
+        *self.health_cache.lock().unwrap() = Some((clock.monotonic(), health.clone()));
+        Ok(health)
+    }
+
+    /// Call any facilitator endpoint with an untyped JSON body and response
+    ///
+    /// `path` is resolved like the typed methods' endpoints, and the API key,
+    /// default headers, timeouts and retry policy all apply. Use this for
+    /// endpoints the SDK doesn't wrap yet. An empty response body is
+    /// returned as `Value::Null`. A `path` that isn't relative to the
+    /// facilitator's API, such as an absolute URL, fails with
+    /// [`QweryError::ConfigError`] before anything is sent.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use qwery_sdk::{Method, QweryClient, Network};
+    /// use serde_json::json;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let client = QweryClient::new(Network::Mainnet)?;
+    ///
+    ///     let response = client
+    ///         .request_raw(Method::POST, "/payments/quote", Some(json!({ "amount": 1.0 })))
+    ///         .await?;
+    ///     println!("{}", response);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn request_raw(
+        &self,
+        method: Method,
+        path: &str,
+        body: Option<serde_json::Value>,
+    ) -> Result<serde_json::Value> {
+        self.request_typed(method, path, body.as_ref()).await
+    }
+
+    /// Call any facilitator endpoint, serializing `body` and deserializing
+    /// the response into your own types
+    ///
+    /// Like [`request_raw`](QweryClient::request_raw), with the API key,
+    /// default headers, timeouts and retry policy applied, but typed on both
+    /// ends. An empty response body is read as JSON `null`, so use `()` or an
+    /// `Option` for endpoints that return nothing. `path` is checked the same
+    /// way, so an absolute URL fails with [`QweryError::ConfigError`].
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use qwery_sdk::{Method, QweryClient, Network};
+    /// use serde::{Deserialize, Serialize};
+    ///
+    /// #[derive(Serialize)]
+    /// struct QuoteRequest {
+    ///     amount: f64,
+    /// }
+    ///
+    /// #[derive(Deserialize)]
+    /// struct Quote {
+    ///     fee: f64,
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let client = QweryClient::new(Network::Mainnet)?;
+    ///
+    ///     let quote: Quote = client
+    ///         .request_typed(Method::POST, "/payments/quote", Some(&QuoteRequest { amount: 1.0 }))
+    ///         .await?;
+    ///     println!("Fee: {}", quote.fee);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn request_typed<B: Serialize + ?Sized, R: DeserializeOwned>(
+        &self,
+        method: Method,
+        path: &str,
+        body: Option<&B>,
+    ) -> Result<R> {
+        let mut req = self.request(method, path)?;
+        if let Some(body) = body {
+            req = req.json(body);
+        }
+
+        self.send(req, path, None).await
+    }
+
+    /// Stop accepting new payments and wait for in-flight ones to finish
+    ///
+    /// Afterwards, creating or settling a payment on this client or any of
+    /// its clones fails with [`QweryError::ClientClosed`]. Creates and
+    /// settlements already running, including their retries, are given up
+    /// to `timeout` to complete. Lookups such as [`get_payment`] and
+    /// [`verify_payment`] keep working.
+    ///
+    /// Returns `false` if some were still running when `timeout` elapsed.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use qwery_sdk::QweryClient;
+    /// use std::time::Duration;
+    ///
+    /// async fn stop(client: &QweryClient) {
+    ///     if !client.shutdown(Duration::from_secs(30)).await {
+    ///         eprintln!("some payments were still in flight");
+    ///     }
+    /// }
+    /// ```
+    ///
+    /// [`get_payment`]: QweryClient::get_payment
+    /// [`verify_payment`]: QweryClient::verify_payment
+    pub async fn shutdown(&self, timeout: Duration) -> bool {
+        self.lifecycle.close(timeout).await
+    }
+
+    /// Whether [`shutdown`](Self::shutdown) has been called on this client or a clone
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn is_closed(&self) -> bool {
+        self.lifecycle.is_closed()
     }
 
     /// Get the current configuration
     pub fn config(&self) -> &QweryConfig {
         &self.config
     }
+
+    /// Create a client for `network` that shares this one's HTTP connection
+    /// pool, credentials, concurrency limit and lifecycle
+    ///
+    /// Verification results are cached per network, so the new client starts
+    /// with an empty cache. An `rpc_url` left at the old network's
+    /// [`default_rpc_url`](Network::default_rpc_url) moves to the new
+    /// network's default; any other `rpc_url` is kept as is.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use qwery_sdk::{QweryClient, Network};
+    ///
+    /// let mainnet = QweryClient::new(Network::Mainnet).unwrap();
+    /// let devnet = mainnet.with_network(Network::Devnet);
+    /// ```
+    pub fn with_network(&self, network: Network) -> QweryClient {
+        let mut client = self.clone();
+        let config = Arc::make_mut(&mut client.config);
+        if config.rpc_url.as_deref() == Some(config.network.default_rpc_url()) {
+            config.rpc_url = Some(network.default_rpc_url().to_string());
+            #[cfg(all(feature = "signing", not(target_arch = "wasm32")))]
+            {
+                client.rpc = Arc::default();
+            }
+        }
+        config.network = network;
+        client.verify_cache = Arc::new(Mutex::new(VerifyCache::new(config.verify_cache_size)));
+        client
+    }
+
+    /// Create a handle whose operations all finish by `deadline`
+    ///
+    /// Requests, retry backoff and confirmation waits made through the handle
+    /// stop at the deadline with [`QweryError::DeadlineExceeded`], whatever
+    /// their own timeouts. The handle shares everything else with this
+    /// client, so it's cheap to create one per incoming request. When this
+    /// client already has an earlier deadline, that one is kept.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use qwery_sdk::{QweryClient, Network};
+    /// use std::time::{Duration, Instant};
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let client = QweryClient::new(Network::Mainnet)?;
+    ///
+    ///     // Whatever is left of the incoming request's budget
+    ///     let scoped = client.with_deadline(Instant::now() + Duration::from_secs(5));
+    ///     let payment = scoped.get_payment("payment_id").await?;
+    ///     println!("Status: {}", payment.status);
+    ///     scoped
+    ///         .wait_for_confirmation("transaction_signature", Duration::from_secs(60))
+    ///         .await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn with_deadline(&self, deadline: Instant) -> QweryClient {
+        let mut client = self.clone();
+        client.deadline = Some(self.deadline.map_or(deadline, |current| current.min(deadline)));
+        client
+    }
+
+    /// Time left before the [`with_deadline`](Self::with_deadline) deadline,
+    /// if one is set
+    fn time_to_deadline(&self) -> Result<Option<Duration>> {
+        match self.deadline {
+            Some(deadline) => match deadline.checked_duration_since(Instant::now()) {
+                Some(remaining) if !remaining.is_zero() => Ok(Some(remaining)),
+                _ => Err(QweryError::DeadlineExceeded),
+            },
+            None => Ok(None),
+        }
+    }
+
+    /// Run `future`, giving up with [`QweryError::DeadlineExceeded`] if the
+    /// deadline passes first
+    async fn before_deadline<F: Future>(&self, future: F) -> Result<F::Output> {
+        let Some(remaining) = self.time_to_deadline()? else {
+            return Ok(future.await);
+        };
+        match future::select(Box::pin(future), Box::pin(runtime::sleep(remaining))).await {
+            Either::Left((output, _)) => Ok(output),
+            Either::Right(_) => Err(QweryError::DeadlineExceeded),
+        }
+    }
+
+    /// Shared RPC client slot, filled on first chain access
+    #[cfg(all(feature = "signing", not(target_arch = "wasm32")))]
+    pub(crate) fn lazy_rpc(&self) -> &LazyRpcClient {
+        &self.rpc
+    }
+
+    /// Reject payments above [`QweryConfig::max_payment_amount`]
+    fn check_amount_cap(&self, token: &str, amount: f64) -> Result<()> {
+        let Some(ref caps) = self.config.max_payment_amount else {
+            return Ok(());
+        };
+        let Ok(token) = Token::from_str(token) else {
+            return Ok(());
+        };
+
+        let units = token.to_base_units(amount);
+        match caps.get(&token) {
+            Some(&cap) if units > cap => Err(QweryError::ConfigError(format!(
+                "payment of {} exceeds the configured maximum of {}",
+                token.format_amount(units),
+                token.format_amount(cap)
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    /// Check that a payment or result is on the network this client uses
+    fn check_network(&self, network: &str) -> Result<()> {
+        let expected = self.config.network.as_str();
+        if network != expected {
+            return Err(QweryError::NetworkMismatch {
+                expected: expected.to_string(),
+                actual: network.to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Report the outcome of creating a payment to the metrics observer
+    fn record_created(&self, result: std::result::Result<&PaymentResponse, &QweryError>) {
+        if let (Some(ref unsettled), Ok(payment)) = (&self.unsettled, result) {
+            unsettled.created(&payment.payment_id);
+        }
+        if let Some(ref metrics) = self.config.metrics {
+            match result {
+                Ok(payment) => {
+                    if let Ok(token) = Token::from_str(&payment.token) {
+                        metrics.on_payment_created(
+                            &payment.payment_id,
+                            token,
+                            token.to_base_units(payment.amount),
+                        );
+                    }
+                }
+                Err(e) => metrics.on_payment_failed(e),
+            }
+        }
+    }
+
+    /// Fail with [`QweryError::AmountTooSmall`] if a payment is below
+    /// [`Token::minimum_amount`]
+    fn check_minimum_amount(&self, token: Token, request: &PaymentRequest) -> Result<()> {
+        let minimum = token.minimum_amount(self.config.network);
+        if token.to_base_units(request.amount) < minimum {
+            return Err(QweryError::AmountTooSmall { token, minimum });
+        }
+        Ok(())
+    }
+
+    /// Fail with [`QweryError::AmountTooSmall`] if SOL below
+    /// [`RENT_EXEMPT_MINIMUM_LAMPORTS`] is sent to an account that doesn't
+    /// exist yet
+    ///
+    /// Only looks the recipient up, through `rpc_url`, when the amount is
+    /// below rent exemption; without `rpc_url` the check is skipped.
+    #[cfg_attr(
+        not(all(feature = "signing", not(target_arch = "wasm32"))),
+        allow(unused_variables)
+    )]
+    async fn check_rent_exemption(&self, token: Token, request: &PaymentRequest) -> Result<()> {
+        #[cfg(all(feature = "signing", not(target_arch = "wasm32")))]
+        if token == Token::Sol
+            && self.config.rpc_url.is_some()
+            && token.to_base_units(request.amount) < RENT_EXEMPT_MINIMUM_LAMPORTS
+        {
+            if let Ok(recipient) = Pubkey::from_str(&request.recipient) {
+                if !self.account_exists(&recipient).await? {
+                    return Err(QweryError::AmountTooSmall {
+                        token,
+                        minimum: token
+                            .minimum_amount(self.config.network)
+                            .max(RENT_EXEMPT_MINIMUM_LAMPORTS),
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Validate a payment before it's created, rounding its amount if the
+    /// rounding policy allows
+    ///
+    /// Runs the request's own checks, then the minimum amount, amount cap
+    /// and rent exemption checks for its token.
+    async fn check_payment(&self, request: &mut PaymentRequest) -> Result<()> {
+        request.validate()?;
+        let token = Token::from_str(&request.token).ok();
+        if let Some(token) = token {
+            request.amount = self.config.rounding_policy.apply(token, request.amount)?;
+            self.check_minimum_amount(token, request)?;
+        }
+        self.check_amount_cap(&request.token, request.amount)?;
+        if let Some(token) = token {
+            self.check_rent_exemption(token, request).await?;
+        }
+        Ok(())
+    }
+
+    /// Fail with [`QweryError::FacilitatorUnhealthy`] if `preflight_health_check`
+    /// is set and the facilitator reports the network isn't operational
+    async fn check_health(&self) -> Result<()> {
+        if !self.config.preflight_health_check {
+            return Ok(());
+        }
+
+        let health = self.health_cached(PREFLIGHT_HEALTH_TTL).await?;
+        match health.network_outage(self.config.network) {
+            Some(status) => Err(QweryError::FacilitatorUnhealthy {
+                network_status: status.to_string(),
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// Build the body describing a payment for the facilitator
+    fn payment_body<'a>(&self, request: &'a PaymentRequest) -> CreatePaymentBody<'a> {
+        CreatePaymentBody {
+            request,
+            network: self.config.network.as_str(),
+        }
+    }
+
+    /// Resolve an endpoint path such as `/payments/create` against the base URL
+    ///
+    /// Paths reach here from [`request_raw`](Self::request_raw), so anything
+    /// that would send the credentials elsewhere, such as an absolute URL or
+    /// a path climbing out of the base path, is rejected.
+    pub(crate) fn endpoint_url(&self, path: &str) -> Result<Url> {
+        let invalid = |reason: &str| {
+            QweryError::ConfigError(format!("invalid endpoint path {:?}: {}", path, reason))
+        };
+
+        if Url::parse(path).is_ok() || path.starts_with("//") || path.starts_with("\\\\") {
+            return Err(invalid("must be relative to the facilitator URL"));
+        }
+        let url = self
+            .base_url
+            .join(path.trim_start_matches('/'))
+            .map_err(|e| invalid(&e.to_string()))?;
+        if url.origin() != self.base_url.origin() || !url.path().starts_with(self.base_url.path()) {
+            return Err(invalid("resolves outside the facilitator API"));
+        }
+        Ok(url)
+    }
+
+    /// Build a request to a facilitator endpoint
+    ///
+    /// The bearer token is attached by [`send`](Self::send) so that it is
+    /// current on every retry.
+    pub(crate) fn request(&self, method: Method, path: &str) -> Result<RequestBuilder> {
+        Ok(self
+            .http_client
+            .request(method, self.endpoint_url(path)?)
+            .headers(self.config.default_headers.clone())
+            .header(API_VERSION_HEADER, API_VERSION))
+    }
+
+    /// Current bearer token, if the client is configured with credentials
+    pub(crate) async fn access_token(&self) -> Result<Option<SecretString>> {
+        match self.auth {
+            Some(ref auth) => Ok(Some(auth.get().await?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Send a request and deserialize the JSON response
+    ///
+    /// Rate-limited (429) and unavailable (503) responses are retried
+    /// according to the configured [`RetryPolicy`], honouring `Retry-After`.
+    /// `endpoint` and `payment_id` are only used to give API errors context.
+    pub(crate) async fn send<T: DeserializeOwned>(
+        &self,
+        req: RequestBuilder,
+        endpoint: &str,
+        payment_id: Option<&str>,
+    ) -> Result<T> {
+        let (body, _) = self.send_with_headers(req, endpoint, payment_id).await?;
+        Ok(body)
+    }
+
+    /// Like [`send`](Self::send), also returning the response headers
+    pub(crate) async fn send_with_headers<T: DeserializeOwned>(
+        &self,
+        req: RequestBuilder,
+        endpoint: &str,
+        payment_id: Option<&str>,
+    ) -> Result<(T, HeaderMap)> {
+        let (req, request_id) = with_request_id(req);
+        let span = tracing::debug_span!(
+            "qwery_request",
+            endpoint,
+            request_id = %request_id,
+            payment_id,
+        );
+        async {
+            // The permit is held until the response body has been read
+            let (response, _permit) =
+                self.send_attempts(req, endpoint, payment_id, &request_id).await?;
+            let headers = response.headers().clone();
+            let body = self.before_deadline(response.bytes()).await??;
+            Ok((parse_body(&body, endpoint, &request_id)?, headers))
+        }
+        .instrument(span)
+        .await
+    }
+
+    /// Send a request, retrying as configured, and return the successful
+    /// response with its body unread
+    ///
+    /// The request slot permit, if any, should be held until the body is read.
+    async fn send_attempts(
+        &self,
+        req: RequestBuilder,
+        endpoint: &str,
+        payment_id: Option<&str>,
+        request_id: &str,
+    ) -> Result<(reqwest::Response, Option<OwnedSemaphorePermit>)> {
+        let policy = &self.config.retry_policy;
+        let started = Instant::now();
+        let mut attempt = 0;
+
+        let mut reauthorized = false;
+        let (response, permit) = loop {
+            let mut current = req.try_clone().ok_or_else(|| {
+                QweryError::ConfigError(format!("request to {} cannot be retried", endpoint))
+            })?;
+            #[cfg(not(target_arch = "wasm32"))]
+            if let Some(remaining) = policy.remaining(started.elapsed()) {
+                current = current.timeout(remaining.min(self.config.timeout));
+            }
+            if let Some(token) = self.access_token().await? {
+                current = current.bearer_auth(token.expose_secret());
+            }
+            let permit = match self.request_slots {
+                Some(ref slots) => Some(
+                    self.before_deadline(Arc::clone(slots).acquire_owned())
+                        .await?
+                        .map_err(|_| QweryError::ClientClosed)?,
+                ),
+                None => None,
+            };
+            tracing::debug!(attempt, "sending request");
+            let response = self.before_deadline(current.send()).await?.map_err(|e| {
+                tracing::debug!(error = %e, "request failed");
+                QweryError::from(e)
+            })?;
+
+            let status = response.status();
+            tracing::debug!(status = status.as_u16(), "received response");
+            // A cached token may have been revoked or expired early
+            if status == StatusCode::UNAUTHORIZED && !reauthorized {
+                if let Some(ref auth) = self.auth {
+                    auth.invalidate();
+                    reauthorized = true;
+                    continue;
+                }
+            }
+            if status != StatusCode::TOO_MANY_REQUESTS && status != StatusCode::SERVICE_UNAVAILABLE {
+                break (response, permit);
+            }
+
+            let retry_after = parse_retry_after(response.headers());
+            let delay = retry_after.unwrap_or_else(|| policy.backoff(attempt));
+            if policy.should_retry(attempt, started.elapsed(), delay) {
+                tracing::debug!(?retry_after, "retrying after status {}", status);
+                drop(permit);
+                self.before_deadline(runtime::sleep(delay)).await?;
+                attempt += 1;
+                continue;
+            }
+
+            match retry_after {
+                Some(retry_after) => return Err(QweryError::RateLimited { retry_after }),
+                None if status == StatusCode::TOO_MANY_REQUESTS => {
+                    return Err(QweryError::RateLimited {
+                        retry_after: policy.backoff(attempt),
+                    })
+                }
+                None => break (response, permit),
+            }
+        };
+
+        if !response.status().is_success() {
+            let payment_required = response.status() == StatusCode::PAYMENT_REQUIRED;
+            let headers = payment_required.then(|| response.headers().clone());
+            let error_text = read_error_body(response, self.config.max_error_body_size).await;
+            if let Some(headers) = headers {
+                if let Ok(challenge) = x402::parse_payment_required(&headers, error_text.as_bytes()) {
+                    return Err(QweryError::PaymentRequired {
+                        challenge: Box::new(challenge),
+                    });
+                }
+            }
+            return Err(QweryError::ApiError {
+                endpoint: endpoint.to_string(),
+                payment_id: payment_id.map(str::to_string),
+                message: error_text,
+                request_id: request_id.to_string(),
+            });
+        }
+
+        Ok((response, permit))
+    }
+}
+
+/// Builder for [`QweryClient`], created with [`QweryClient::builder`]
+///
+/// Settings that aren't set keep their [`QweryConfig`] defaults.
+#[derive(Debug, Clone, Default)]
+pub struct QweryClientBuilder {
+    config: QweryConfig,
+    auto_network: bool,
+}
+
+impl QweryClientBuilder {
+    /// Set the network to use
+    pub fn network(&mut self, network: Network) -> &mut Self {
+        self.config.network = network;
+        self
+    }
+
+    /// Set the facilitator API URL
+    pub fn facilitator_url(&mut self, url: impl Into<String>) -> &mut Self {
+        self.config.facilitator_url = url.into();
+        self
+    }
+
+    /// Set a path prefix for every endpoint, e.g. `v1`
+    pub fn api_base_path(&mut self, base_path: impl Into<String>) -> &mut Self {
+        self.config.api_base_path = Some(base_path.into());
+        self
+    }
+
+    /// Set the API key sent as a bearer token
+    pub fn api_key(&mut self, api_key: impl Into<SecretString>) -> &mut Self {
+        self.config.api_key = Some(api_key.into());
+        self
+    }
+
+    /// Set the Solana RPC endpoint used for chain reads
+    pub fn rpc_url(&mut self, rpc_url: impl Into<String>) -> &mut Self {
+        self.config.rpc_url = Some(rpc_url.into());
+        self
+    }
+
+    /// Fetch bearer tokens from `provider` instead of sending a static API key
+    pub fn token_provider(&mut self, provider: impl TokenProvider + 'static) -> &mut Self {
+        self.config.token_provider = Some(Arc::new(provider));
+        self
+    }
+
+    /// Read the time from `clock` instead of the system clock
+    pub fn clock(&mut self, clock: impl Clock + 'static) -> &mut Self {
+        self.config.clock = Arc::new(clock);
+        self
+    }
+
+    /// Set the timeout for each HTTP request
+    pub fn timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.config.timeout = timeout;
+        self
+    }
+
+    /// Set the timeout for establishing a connection
+    pub fn connect_timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.config.connect_timeout = timeout;
+        self
+    }
+
+    /// Set the retry behaviour for rate-limited and unavailable responses
+    pub fn retry_policy(&mut self, retry_policy: RetryPolicy) -> &mut Self {
+        self.config.retry_policy = retry_policy;
+        self
+    }
+
+    /// Build the client
+    ///
+    /// Returns [`QweryError::ConfigError`] if `facilitator_url` is not a
+    /// valid `http` or `https` URL.
+    pub fn build(&self) -> Result<QweryClient> {
+        if self.auto_network {
+            return Err(QweryError::ConfigError(
+                "auto_network needs the facilitator, use build_async".to_string(),
+            ));
+        }
+        QweryClient::with_config(self.config.clone())
+    }
+
+    /// Take the network from the facilitator instead of [`network`](Self::network)
+    ///
+    /// The facilitator is asked which network it serves when the client is
+    /// built with [`build_async`](Self::build_async); see
+    /// [`QweryClient::detect_network`].
+    pub fn auto_network(&mut self) -> &mut Self {
+        self.auto_network = true;
+        self
+    }
+
+    /// Build the client, probing the facilitator for its network if
+    /// [`auto_network`](Self::auto_network) is set
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use qwery_sdk::QweryClient;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let client = QweryClient::builder()
+    ///         .facilitator_url("https://devnet.facilitator.example.com")
+    ///         .auto_network()
+    ///         .build_async()
+    ///         .await?;
+    ///     println!("Network: {:?}", client.config().network);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn build_async(&self) -> Result<QweryClient> {
+        let mut client = QweryClient::with_config(self.config.clone())?;
+        if self.auto_network {
+            let network = client.detect_network().await?;
+            Arc::make_mut(&mut client.config).network = network;
+        }
+        Ok(client)
+    }
+}
+
+/// Tag a request with a fresh `X-Request-Id`, unless the caller already set one
+fn with_request_id(req: RequestBuilder) -> (RequestBuilder, String) {
+    let existing_id = req
+        .try_clone()
+        .and_then(|r| r.build().ok())
+        .and_then(|r| Some(r.headers().get(REQUEST_ID_HEADER)?.to_str().ok()?.to_string()));
+    match existing_id {
+        Some(request_id) => (req, request_id),
+        None => {
+            let request_id = Uuid::new_v4().to_string();
+            (req.header(REQUEST_ID_HEADER, &request_id), request_id)
+        }
+    }
+}
+
+/// Reject headers the client sets itself, so they aren't silently overridden
+fn check_reserved_headers(headers: &HeaderMap) -> Result<()> {
+    for name in [AUTHORIZATION.as_str(), CONTENT_TYPE.as_str(), API_VERSION_HEADER] {
+        if headers.contains_key(name) {
+            return Err(QweryError::ConfigError(format!(
+                "header {} is set by the client and cannot be overridden",
+                name
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Check that `url` is an `http(s)` base URL and strip any trailing slash
+fn validate_facilitator_url(url: &str) -> Result<String> {
+    let parsed = reqwest::Url::parse(url)
+        .map_err(|e| QweryError::ConfigError(format!("invalid facilitator_url {:?}: {}", url, e)))?;
+
+    if parsed.scheme() != "http" && parsed.scheme() != "https" {
+        return Err(QweryError::ConfigError(format!(
+            "facilitator_url must be http or https, got {:?}",
+            url
+        )));
+    }
+    if parsed.query().is_some() || parsed.fragment().is_some() {
+        return Err(QweryError::ConfigError(format!(
+            "facilitator_url must not have a query or fragment, got {:?}",
+            url
+        )));
+    }
+
+    Ok(url.trim_end_matches('/').to_string())
+}
+
+/// Run `task` over `items` with up to `concurrency` in flight
+///
+/// `progress` is called as each item completes; the results keep the order
+/// of `items`.
+async fn run_batch<I, T, Fut>(
+    items: Vec<I>,
+    concurrency: usize,
+    task: impl Fn(I) -> Fut,
+    mut progress: impl FnMut(usize, &Result<T>),
+) -> Vec<Result<T>>
+where
+    Fut: Future<Output = Result<T>>,
+{
+    let mut results: Vec<Option<Result<T>>> = (0..items.len()).map(|_| None).collect();
+
+    let mut completed = stream::iter(items.into_iter().enumerate())
+        .map(|(index, item)| {
+            let future = task(item);
+            async move { (index, future.await) }
+        })
+        .buffer_unordered(concurrency.max(1));
+
+    while let Some((index, result)) = completed.next().await {
+        progress(index, &result);
+        results[index] = Some(result);
+    }
+
+    results
+        .into_iter()
+        .map(|result| result.expect("every batch item completes"))
+        .collect()
+}
+
+/// Escape a caller-supplied value for use as a single URL path segment
+///
+/// Empty and dot segments would resolve to a different endpoint, so they
+/// are rejected.
+fn path_segment(value: &str) -> Result<String> {
+    if matches!(value, "" | "." | "..") {
+        return Err(QweryError::ConfigError(format!("invalid path segment {:?}", value)));
+    }
+    Ok(crate::uri::encode(value))
+}
+
+/// Check every required signer has a valid signature before submission
+///
+/// The fee payer's slot may still be empty, since the facilitator adds its
+/// signature as fee payer at settlement, but any signature that is present
+/// must be valid.
+#[cfg(feature = "signing")]
+fn check_signatures(transaction: &Transaction) -> Result<()> {
+    let message = &transaction.message;
+    let message_data = message.serialize();
+    let required = message.header.num_required_signatures as usize;
+
+    let missing: Vec<Pubkey> = message
+        .account_keys
+        .iter()
+        .take(required)
+        .enumerate()
+        .filter(|&(index, key)| match transaction.signatures.get(index) {
+            Some(signature) if *signature != Signature::default() => {
+                !signature.verify(key.as_ref(), &message_data)
+            }
+            _ => index != 0,
+        })
+        .map(|(_, key)| *key)
+        .collect();
+
+    if !missing.is_empty() {
+        return Err(QweryError::IncompleteSignatures { missing });
+    }
+    Ok(())
+}
+
+/// Encode a signed transaction for `payment`, rejecting it if it's too large to send
+#[cfg(feature = "signing")]
+fn signed_payment(payment: &PaymentResponse, transaction: &Transaction) -> Result<SignedPayment> {
+    let signed_bytes = bincode::serialize(transaction)
+        .map_err(|e| QweryError::SolanaError(e.to_string()))?;
+    if signed_bytes.len() > MAX_TRANSACTION_SIZE {
+        return Err(QweryError::TransactionTooLarge { size: signed_bytes.len() });
+    }
+
+    Ok(SignedPayment {
+        payment_id: payment.payment_id.clone(),
+        signed_transaction_base64: BASE64.encode(signed_bytes),
+        signature: transaction
+            .signatures
+            .first()
+            .ok_or_else(|| QweryError::SigningError("transaction has no signatures".to_string()))?
+            .to_string(),
+    })
+}
+
+/// Body of a create or estimate request: the payment plus the client's network
+///
+/// Field names and omitted fields come from [`PaymentRequest`]'s `Serialize`.
+#[derive(Serialize)]
+struct CreatePaymentBody<'a> {
+    #[serde(flatten)]
+    request: &'a PaymentRequest,
+    network: &'static str,
+}
+
+/// Body of a create request for a split payment
+#[derive(Serialize)]
+struct CreateSplitPaymentBody<'a> {
+    amount: f64,
+    token: &'static str,
+    splits: &'a [PaymentSplit],
+    network: &'static str,
+}
+
+/// Body of a settle request, with the submit options when any were given
+#[derive(Serialize)]
+struct SettleBody<'a> {
+    #[serde(flatten)]
+    request: &'a SettleRequest,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    options: Option<SettleOptions>,
+}
+
+/// Turn an unsuccessful settlement into the most specific error available
+fn settlement_error(payment_id: &str, response: SettleResponse) -> QweryError {
+    if let Some(ref message) = response.error {
+        let lowercase = message.to_ascii_lowercase();
+        if lowercase.contains("blockhash not found") || lowercase.contains("blockhashnotfound") {
+            return QweryError::BlockhashExpired {
+                payment_id: payment_id.to_string(),
+                message: message.clone(),
+            };
+        }
+        if lowercase.contains("accountnotfound") || lowercase.contains("account not found") {
+            return QweryError::RecipientAccountMissing {
+                payment_id: payment_id.to_string(),
+                message: message.clone(),
+            };
+        }
+    }
+
+    QweryError::SettlementFailed {
+        status: response.status,
+        error: response.error,
+        logs: response.logs,
+    }
+}
+
+/// Programs whose custom error 0x1 means an account lacked funds: the System
+/// program's `ResultWithNegativeLamports` and SPL Token's `InsufficientFunds`
+const INSUFFICIENT_FUNDS_PROGRAMS: [&str; 3] = [
+    "11111111111111111111111111111111",
+    "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA",
+    "TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb",
+];
+
+/// Whether a settlement or simulation error means the payer lacked funds
+fn is_insufficient_funds(message: &str) -> bool {
+    let lowercase = message.to_ascii_lowercase();
+    if lowercase.contains("insufficient funds")
+        || lowercase.contains("insufficientfunds")
+        || lowercase.contains("insufficient lamports")
+    {
+        return true;
+    }
+
+    // Other programs use 0x1 for unrelated errors, so only count it in a
+    // program log line naming one of the above
+    INSUFFICIENT_FUNDS_PROGRAMS.iter().any(|program| {
+        let marker = format!("Program {} failed: custom program error: 0x1", program);
+        message.match_indices(&marker).any(|(i, _)| {
+            !message[i + marker.len()..].starts_with(|c: char| c.is_ascii_hexdigit())
+        })
+    })
+}
+
+/// Whether a request failed in transport, so the facilitator may or may not
+/// have processed it
+fn is_transport_error(error: &QweryError) -> bool {
+    matches!(
+        error,
+        QweryError::RequestError(_)
+            | QweryError::ConnectTimeout(_)
+            | QweryError::ReadTimeout(_)
+            | QweryError::ConnectionRefused(_)
+    )
+}
+
+/// Run `future` until it completes or `cancel` is triggered
+async fn cancellable<T>(
+    cancel: &CancellationToken,
+    future: impl Future<Output = Result<T>>,
+) -> Result<T> {
+    tokio::select! {
+        _ = cancel.cancelled() => Err(QweryError::Cancelled),
+        result = future => result,
+    }
+}
+
+/// Marker appended to error bodies cut off at `max_error_body_size`
+const TRUNCATED_MARKER: &str = "... [truncated]";
+
+/// Read at most `limit` bytes of an error body, appending a marker if cut off
+///
+/// Read errors are ignored since the status code already reports the failure.
+async fn read_error_body(response: reqwest::Response, limit: usize) -> String {
+    #[cfg(not(target_arch = "wasm32"))]
+    let (body, truncated) = {
+        let mut response = response;
+        let mut body = Vec::new();
+        let mut truncated = false;
+        while let Ok(Some(chunk)) = response.chunk().await {
+            let remaining = limit - body.len();
+            if chunk.len() > remaining {
+                body.extend_from_slice(&chunk[..remaining]);
+                truncated = true;
+                break;
+            }
+            body.extend_from_slice(&chunk);
+        }
+        (body, truncated)
+    };
+
+    // The fetch backend can only read the whole body
+    #[cfg(target_arch = "wasm32")]
+    let (body, truncated) = {
+        let mut body = response.bytes().await.map(|b| b.to_vec()).unwrap_or_default();
+        let truncated = body.len() > limit;
+        body.truncate(limit);
+        (body, truncated)
+    };
+
+    let mut text = String::from_utf8_lossy(&body).into_owned();
+    if truncated {
+        text.push_str(TRUNCATED_MARKER);
+    }
+    text
+}
+
+/// Deserialize a success body, treating an empty body as JSON `null`
+///
+/// Endpoints that return no content can be called with `T = ()` or an
+/// `Option`; for anything else an empty or malformed body is reported as
+/// [`QweryError::InvalidResponse`].
+fn parse_body<T: DeserializeOwned>(body: &[u8], endpoint: &str, request_id: &str) -> Result<T> {
+    let invalid = |message: String| QweryError::InvalidResponse {
+        endpoint: endpoint.to_string(),
+        request_id: request_id.to_string(),
+        length: body.len(),
+        message,
+    };
+
+    if body.iter().all(u8::is_ascii_whitespace) {
+        return serde_json::from_str("null")
+            .map_err(|_| invalid("expected a JSON body but the response was empty".to_string()));
+    }
+
+    serde_json::from_slice(body).map_err(|e| invalid(e.to_string()))
+}
+
+/// Parse a `Retry-After` header in either delta-seconds or HTTP-date form
+fn parse_retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+
+    let date = httpdate::parse_http_date(value).ok()?;
+    let date = date.duration_since(UNIX_EPOCH).ok()?;
+    Some(date.saturating_sub(runtime::unix_now()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builder_validates_and_normalizes_the_facilitator_url() {
+        let client = QweryClient::builder()
+            .network(Network::Devnet)
+            .facilitator_url("http://localhost:8080/")
+            .build()
+            .unwrap();
+        assert_eq!(client.config().network, Network::Devnet);
+        assert_eq!(client.config().facilitator_url, "http://localhost:8080");
+
+        assert!(matches!(
+            QweryClient::builder().facilitator_url("not a url").build(),
+            Err(QweryError::ConfigError(_))
+        ));
+    }
+
+    #[cfg(all(feature = "signing", not(target_arch = "wasm32")))]
+    #[tokio::test]
+    async fn verify_payment_rejects_results_from_another_network() {
+        let mock = crate::mock_facilitator::MockFacilitator::start(|_| {
+            (
+                200,
+                r#"{"verified":true,"status":"confirmed","confirmations":1,"signature":null,"network":"solana"}"#
+                    .to_string(),
+            )
+        })
+        .await;
+        let client = |network| {
+            QweryClient::with_config(QweryConfig {
+                facilitator_url: mock.url.clone(),
+                network,
+                ..Default::default()
+            })
+            .unwrap()
+        };
+        let signature = Signature::default().to_string();
+
+        assert!(client(Network::Mainnet).verify_payment(&signature).await.is_ok());
+        match client(Network::Devnet).verify_payment(&signature).await {
+            Err(QweryError::NetworkMismatch { expected, actual }) => {
+                assert_eq!(expected, "solana-devnet");
+                assert_eq!(actual, "solana");
+            }
+            other => panic!("expected NetworkMismatch, got {:?}", other),
+        }
+    }
+
+    #[cfg(feature = "signing")]
+    const PAYMENT_JSON: &str = r#"{
+        "payment_id": "pay_123",
+        "transaction": "",
+        "amount": 0.000001,
+        "token": "SOL",
+        "recipient": "9xQeWvG816bUx9EPjHmaT23yvVM2ZWbrrpZb9PusVFin",
+        "network": "solana",
+        "status": "pending"
+    }"#;
+
+    #[tokio::test]
+    async fn create_split_payment_checks_every_split_before_sending() {
+        let client = QweryClient::new(Network::Mainnet).unwrap();
+        let split = |amount| vec![PaymentSplit::new("9xQeWvG816bUx9EPjHmaT23yvVM2ZWbrrpZb9PusVFin", amount)];
+
+        for amount in [-1.0, 0.0, f64::NAN] {
+            let result = client.create_split_payment(split(amount), Token::Usdc).await;
+            assert!(matches!(result, Err(QweryError::ConfigError(_))), "{} was accepted", amount);
+        }
+
+        let result = client.create_split_payment(split(0.000_000_1), Token::Usdc).await;
+        assert!(matches!(result, Err(QweryError::ConfigError(_))));
+        let result = client.create_split_payment(split(0.000_001), Token::Usdc).await;
+        assert!(matches!(result, Err(QweryError::AmountTooSmall { .. })));
+    }
+
+    #[cfg(all(feature = "signing", not(target_arch = "wasm32")))]
+    #[tokio::test]
+    async fn create_split_payment_sends_rounded_splits() {
+        let mock = crate::mock_facilitator::MockFacilitator::start(|_| (200, PAYMENT_JSON.to_string())).await;
+        let client = QweryClient::with_config(QweryConfig {
+            facilitator_url: mock.url.clone(),
+            rounding_policy: RoundingPolicy::Round,
+            ..Default::default()
+        })
+        .unwrap();
+
+        client
+            .create_split_payment(
+                vec![PaymentSplit::new("seller", 9.500_000_4), PaymentSplit::new("marketplace", 0.5)],
+                Token::Usdc,
+            )
+            .await
+            .unwrap();
+
+        let received = mock.received();
+        let body: serde_json::Value = serde_json::from_str(&received[0].body).unwrap();
+        assert_eq!(
+            body,
+            serde_json::json!({
+                "amount": 10.0,
+                "token": "USDC",
+                "splits": [
+                    { "recipient": "seller", "amount": 9.5 },
+                    { "recipient": "marketplace", "amount": 0.5 },
+                ],
+                "network": "solana",
+            })
+        );
+    }
+
+    #[cfg(feature = "signing")]
+    #[tokio::test]
+    async fn dry_run_settles_locally_with_a_marked_signature() {
+        let client = QweryClient::with_config(QweryConfig {
+            dry_run: true,
+            ..Default::default()
+        })
+        .unwrap();
+
+        let signed = bincode::serialize(&Transaction::default()).unwrap();
+        let response = client
+            .settle_payment(SettleRequest {
+                payment_id: "pay_123".to_string(),
+                signed_transaction: BASE64.encode(signed),
+            })
+            .await
+            .unwrap();
+        assert!(response.success);
+        assert_eq!(response.signature.as_deref(), Some("dry-run-pay_123"));
+
+        // Still decoded, so a malformed transaction fails as it would for real
+        let result = client
+            .settle_payment(SettleRequest {
+                payment_id: "pay_123".to_string(),
+                signed_transaction: BASE64.encode(b"not a transaction"),
+            })
+            .await;
+        assert!(matches!(result, Err(QweryError::SolanaError(_))));
+    }
+
+    /// A transfer from `payer` whose fees are paid by `fee_payer`
+    #[cfg(feature = "signing")]
+    fn sponsored_transfer(fee_payer: &Pubkey, payer: &Keypair) -> Transaction {
+        use solana_sdk::signer::Signer;
+
+        let transfer = solana_sdk::system_instruction::transfer(&payer.pubkey(), &Pubkey::new_unique(), 1_000);
+        Transaction::new_with_payer(&[transfer], Some(fee_payer))
+    }
+
+    #[cfg(feature = "signing")]
+    #[test]
+    fn check_signatures_leaves_the_fee_payer_slot_to_the_facilitator() {
+        use solana_sdk::hash::Hash;
+
+        let payer = Keypair::new();
+        let mut transaction = sponsored_transfer(&Pubkey::new_unique(), &payer);
+        transaction.partial_sign(&[&payer], Hash::default());
+
+        assert!(check_signatures(&transaction).is_ok());
+    }
+
+    #[cfg(feature = "signing")]
+    #[test]
+    fn check_signatures_names_unsigned_and_invalid_signers() {
+        use solana_sdk::signer::Signer;
+
+        let fee_payer = Keypair::new();
+        let payer = Keypair::new();
+        let mut transaction = sponsored_transfer(&fee_payer.pubkey(), &payer);
+
+        match check_signatures(&transaction) {
+            Err(QweryError::IncompleteSignatures { missing }) => assert_eq!(missing, [payer.pubkey()]),
+            other => panic!("expected IncompleteSignatures, got {:?}", other),
+        }
+
+        // A fee payer signature over a different message doesn't count
+        transaction.signatures[0] = fee_payer.sign_message(b"something else");
+        transaction.signatures[1] = payer.sign_message(&transaction.message_data());
+        match check_signatures(&transaction) {
+            Err(QweryError::IncompleteSignatures { missing }) => {
+                assert_eq!(missing, [fee_payer.pubkey()])
+            }
+            other => panic!("expected IncompleteSignatures, got {:?}", other),
+        }
+    }
+
+    #[cfg(feature = "signing")]
+    #[test]
+    fn check_signatures_requires_slots_missing_from_the_signature_list() {
+        use solana_sdk::signer::Signer;
+
+        let payer = Keypair::new();
+        let mut transaction = sponsored_transfer(&Pubkey::new_unique(), &payer);
+        transaction.signatures.clear();
+
+        match check_signatures(&transaction) {
+            Err(QweryError::IncompleteSignatures { missing }) => assert_eq!(missing, [payer.pubkey()]),
+            other => panic!("expected IncompleteSignatures, got {:?}", other),
+        }
+    }
+
+    #[cfg(feature = "signing")]
+    #[test]
+    fn signed_payment_without_signature_slots_is_an_error() {
+        let mut transaction = sponsored_transfer(&Pubkey::new_unique(), &Keypair::new());
+        transaction.signatures.clear();
+
+        let payment: PaymentResponse = serde_json::from_str(PAYMENT_JSON).unwrap();
+        assert!(matches!(
+            signed_payment(&payment, &transaction),
+            Err(QweryError::SigningError(_))
+        ));
+    }
+
+    #[test]
+    fn insufficient_funds_is_recognised_by_message_or_program_error() {
+        assert!(is_insufficient_funds("Transfer: insufficient lamports 100, need 2000"));
+        assert!(is_insufficient_funds("Program log: Error: insufficient funds"));
+        assert!(is_insufficient_funds(
+            "Program TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA failed: custom program error: 0x1"
+        ));
+        assert!(is_insufficient_funds(
+            "Program 11111111111111111111111111111111 failed: custom program error: 0x1"
+        ));
+    }
+
+    #[test]
+    fn other_program_errors_are_not_insufficient_funds() {
+        // 0x1 from an unnamed or unrelated program, and longer codes
+        assert!(!is_insufficient_funds(
+            "Error processing Instruction 0: custom program error: 0x1"
+        ));
+        assert!(!is_insufficient_funds(
+            "Program ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL failed: custom program error: 0x1"
+        ));
+        assert!(!is_insufficient_funds(
+            "Program TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA failed: custom program error: 0x11"
+        ));
+        assert!(!is_insufficient_funds("Blockhash not found"));
+    }
+
+    fn client_with_base_path(base_path: &str) -> QweryClient {
+        QweryClient::with_config(QweryConfig {
+            facilitator_url: "https://facilitator.example".to_string(),
+            api_base_path: Some(base_path.to_string()),
+            ..Default::default()
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn endpoint_url_joins_relative_paths() {
+        let client = client_with_base_path("/api/v1");
+
+        let url = client.endpoint_url("/payments/create").unwrap();
+        assert_eq!(url.as_str(), "https://facilitator.example/api/v1/payments/create");
+        let url = client.endpoint_url("payments/quote").unwrap();
+        assert_eq!(url.as_str(), "https://facilitator.example/api/v1/payments/quote");
+    }
+
+    #[test]
+    fn endpoint_url_rejects_other_hosts_and_escapes() {
+        let client = client_with_base_path("/api/v1");
+
+        for path in [
+            "https://other.example/x",
+            "//other.example/x",
+            "\\\\other.example/x",
+            "/\\\\other.example/x",
+            "../../admin",
+            "/../v2/payments",
+            "mailto:someone@example.com",
+        ] {
+            assert!(
+                matches!(client.endpoint_url(path), Err(QweryError::ConfigError(_))),
+                "{:?} was accepted",
+                path
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn shutdown_refuses_new_payments_on_every_clone() {
+        let client = QweryClient::new(Network::Devnet).unwrap();
+        let clone = client.clone();
+        assert!(client.shutdown(Duration::from_secs(30)).await);
+
+        let result = clone.create_payment(PaymentRequest::default()).await;
+        assert!(matches!(result, Err(QweryError::ClientClosed)));
+    }
+
+    /// `PAYMENT_JSON` carrying `transaction` built on `blockhash`
+    #[cfg(all(feature = "signing", not(target_arch = "wasm32")))]
+    fn payment_on(transaction: &Transaction, blockhash: solana_sdk::hash::Hash) -> String {
+        let mut transaction = transaction.clone();
+        transaction.message.recent_blockhash = blockhash;
+        let encoded = BASE64.encode(bincode::serialize(&transaction).unwrap());
+
+        let mut payment: serde_json::Value = serde_json::from_str(PAYMENT_JSON).unwrap();
+        payment["transaction"] = encoded.into();
+        payment.to_string()
+    }
+
+    /// Serve a payment whose first settlement fails on an expired blockhash,
+    /// and which the facilitator rebuilds on `refetched_blockhash`
+    #[cfg(all(feature = "signing", not(target_arch = "wasm32")))]
+    async fn expiring_facilitator(
+        transaction: &Transaction,
+        refetched_blockhash: solana_sdk::hash::Hash,
+    ) -> crate::mock_facilitator::MockFacilitator {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let refetched = payment_on(transaction, refetched_blockhash);
+        let settles = AtomicUsize::new(0);
+        crate::mock_facilitator::MockFacilitator::start(move |request| {
+            match (request.method.as_str(), request.path.as_str()) {
+                // RPC: every blockhash is still valid when checked before signing
+                ("POST", "/") if request.body.contains("getVersion") => (
+                    200,
+                    r#"{"jsonrpc":"2.0","result":{"solana-core":"1.18.26","feature-set":0},"id":1}"#
+                        .to_string(),
+                ),
+                ("POST", "/") => (
+                    200,
+                    r#"{"jsonrpc":"2.0","result":{"context":{"slot":1},"value":true},"id":1}"#
+                        .to_string(),
+                ),
+                ("GET", "/payments/pay_123") => (200, refetched.clone()),
+                ("POST", "/payments/settle") if settles.fetch_add(1, Ordering::SeqCst) == 0 => (
+                    200,
+                    r#"{"success":false,"status":"failed","error":"Blockhash not found"}"#.to_string(),
+                ),
+                ("POST", "/payments/settle") => (
+                    200,
+                    r#"{"success":true,"signature":"sig","status":"settled"}"#.to_string(),
+                ),
+                _ => (404, "{}".to_string()),
+            }
+        })
+        .await
+    }
+
+    #[cfg(all(feature = "signing", not(target_arch = "wasm32")))]
+    fn client_for(mock: &crate::mock_facilitator::MockFacilitator) -> QweryClient {
+        QweryClient::with_config(QweryConfig {
+            facilitator_url: mock.url.clone(),
+            rpc_url: Some(mock.url.clone()),
+            refresh_blockhash: true,
+            ..Default::default()
+        })
+        .unwrap()
+    }
+
+    #[cfg(all(feature = "signing", not(target_arch = "wasm32")))]
+    #[tokio::test]
+    async fn sign_and_settle_signs_the_rebuilt_transaction_after_blockhash_expiry() {
+        use solana_sdk::hash::Hash;
+
+        let payer = Keypair::new();
+        let transaction = sponsored_transfer(&Pubkey::new_unique(), &payer);
+        let stale = Hash::new_unique();
+        let fresh = Hash::new_unique();
+        let mock = expiring_facilitator(&transaction, fresh).await;
+        let client = client_for(&mock);
+
+        let payment: PaymentResponse = serde_json::from_str(&payment_on(&transaction, stale)).unwrap();
+        let result = client.sign_and_settle(&payment, &payer).await.unwrap();
+        assert_eq!(result.signature.as_deref(), Some("sig"));
+
+        let settled: Vec<Transaction> = mock
+            .received()
+            .iter()
+            .filter(|request| request.path == "/payments/settle")
+            .map(|request| {
+                let body: serde_json::Value = serde_json::from_str(&request.body).unwrap();
+                let bytes = BASE64.decode(body["signed_transaction"].as_str().unwrap()).unwrap();
+                bincode::deserialize(&bytes).unwrap()
+            })
+            .collect();
+        assert_eq!(settled.len(), 2);
+        assert_eq!(settled[0].message.recent_blockhash, stale);
+        assert_eq!(settled[1].message.recent_blockhash, fresh);
+        assert!(check_signatures(&settled[1]).is_ok());
+    }
+
+    #[cfg(all(feature = "signing", not(target_arch = "wasm32")))]
+    #[tokio::test]
+    async fn sign_and_settle_returns_blockhash_expired_when_nothing_was_rebuilt() {
+        use solana_sdk::hash::Hash;
+
+        let payer = Keypair::new();
+        let transaction = sponsored_transfer(&Pubkey::new_unique(), &payer);
+        let stale = Hash::new_unique();
+        let mock = expiring_facilitator(&transaction, stale).await;
+        let client = client_for(&mock);
+
+        let payment: PaymentResponse = serde_json::from_str(&payment_on(&transaction, stale)).unwrap();
+        let result = client.sign_and_settle(&payment, &payer).await;
+        assert!(
+            matches!(result, Err(QweryError::BlockhashExpired { .. })),
+            "expected BlockhashExpired, got {:?}",
+            result
+        );
+    }
+
+    #[tokio::test]
+    async fn request_typed_refuses_absolute_urls_before_sending() {
+        let client = client_with_base_path("/api/v1");
+
+        let result: Result<serde_json::Value> = client
+            .request_typed(Method::GET, "https://other.example/steal", None::<&()>)
+            .await;
+        assert!(matches!(result, Err(QweryError::ConfigError(_))));
+    }
+
+    #[test]
+    fn with_network_moves_only_a_default_rpc_url() {
+        let mainnet = QweryClient::with_config(QweryConfig {
+            rpc_url: Some(Network::Mainnet.default_rpc_url().to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+        let devnet = mainnet.with_network(Network::Devnet);
+        assert_eq!(devnet.config().network, Network::Devnet);
+        assert_eq!(devnet.config().facilitator_url, mainnet.config().facilitator_url);
+        assert_eq!(devnet.config().rpc_url.as_deref(), Some(Network::Devnet.default_rpc_url()));
+
+        let custom = QweryClient::with_config(QweryConfig {
+            rpc_url: Some("http://localhost:8899".to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+        let devnet = custom.with_network(Network::Devnet);
+        assert_eq!(devnet.config().rpc_url.as_deref(), Some("http://localhost:8899"));
+    }
 }
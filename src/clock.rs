@@ -0,0 +1,176 @@
+//! Time source for expiry checks and timeouts
+//!
+//! The client reads the time through [`QweryConfig::clock`], so tests can
+//! swap in a [`MockClock`] and move time forward by hand instead of waiting.
+//!
+//! [`QweryConfig::clock`]: crate::QweryConfig::clock
+
+use crate::runtime::{self, Instant};
+use std::fmt::Debug;
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+/// Source of the current time
+///
+/// # Example
+///
+/// ```rust
+/// use qwery_sdk::clock::MockClock;
+/// use qwery_sdk::QweryConfig;
+/// use std::sync::Arc;
+/// use std::time::Duration;
+///
+/// let clock = Arc::new(MockClock::new(Duration::from_secs(1_700_000_000)));
+/// let config = QweryConfig {
+///     clock: clock.clone(),
+///     ..Default::default()
+/// };
+///
+/// // An hour later, as far as the client can tell
+/// clock.advance(Duration::from_secs(3600));
+/// ```
+pub trait Clock: Debug + Send + Sync {
+    /// Wall-clock time as the duration since the Unix epoch
+    fn now(&self) -> Duration;
+
+    /// Time since an arbitrary fixed point, for measuring elapsed time
+    ///
+    /// Never goes backwards. Defaults to [`now`](Clock::now).
+    fn monotonic(&self) -> Duration {
+        self.now()
+    }
+}
+
+/// The system clock, used unless [`QweryConfig::clock`] says otherwise
+///
+/// [`QweryConfig::clock`]: crate::QweryConfig::clock
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Duration {
+        runtime::unix_now()
+    }
+
+    fn monotonic(&self) -> Duration {
+        static ORIGIN: OnceLock<Instant> = OnceLock::new();
+        ORIGIN.get_or_init(Instant::now).elapsed()
+    }
+}
+
+/// A clock that only moves when told to, for tests
+#[derive(Debug, Default)]
+pub struct MockClock(Mutex<Duration>);
+
+impl MockClock {
+    /// Create a clock reading `now` since the Unix epoch
+    pub fn new(now: Duration) -> Self {
+        Self(Mutex::new(now))
+    }
+
+    /// Move the clock forward by `duration`
+    pub fn advance(&self, duration: Duration) {
+        *self.0.lock().unwrap() += duration;
+    }
+
+    /// Set the clock to `now` since the Unix epoch
+    pub fn set(&self, now: Duration) {
+        *self.0.lock().unwrap() = now;
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> Duration {
+        *self.0.lock().unwrap()
+    }
+}
+
+/// Parse an RFC 3339 timestamp such as `2025-01-01T00:00:00Z` into the
+/// duration since the Unix epoch
+pub(crate) fn parse_rfc3339(value: &str) -> Option<Duration> {
+    let (date, time) = value.split_once(['T', 't', ' '])?;
+
+    let mut date_parts = date.splitn(3, '-').map(|part| part.parse::<i64>().ok());
+    let (year, month, day) = (date_parts.next()??, date_parts.next()??, date_parts.next()??);
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+
+    let (time, offset) = time.split_at(time.find(['Z', 'z', '+', '-'])?);
+    let mut time_parts = time.splitn(3, ':');
+    let hour: i64 = time_parts.next()?.parse().ok()?;
+    let minute: i64 = time_parts.next()?.parse().ok()?;
+    let second = time_parts.next()?;
+    let (second, fraction) = second.split_once('.').unwrap_or((second, ""));
+    let second: i64 = second.parse().ok()?;
+    if hour > 23 || minute > 59 || second > 60 {
+        return None;
+    }
+    let nanos = match fraction {
+        "" => 0,
+        digits => format!("{:0<9}", digits.get(..digits.len().min(9))?).parse().ok()?,
+    };
+
+    let offset = match offset {
+        "Z" | "z" => 0,
+        offset => {
+            let (hours, minutes) = offset[1..].split_once(':')?;
+            let seconds = hours.parse::<i64>().ok()? * 3600 + minutes.parse::<i64>().ok()? * 60;
+            if offset.starts_with('-') { -seconds } else { seconds }
+        }
+    };
+
+    // Days since the epoch in the proleptic Gregorian calendar
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146_097 + day_of_era - 719_468;
+
+    let seconds = days * 86_400 + hour * 3600 + minute * 60 + second - offset;
+    Some(Duration::new(u64::try_from(seconds).ok()?, nanos))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mock_clock_only_moves_when_told() {
+        let clock = MockClock::new(Duration::from_secs(1_700_000_000));
+        let started = clock.monotonic();
+
+        clock.advance(Duration::from_secs(30));
+        assert_eq!(clock.monotonic() - started, Duration::from_secs(30));
+        assert_eq!(clock.now(), Duration::from_secs(1_700_000_030));
+
+        clock.set(Duration::from_secs(5));
+        assert_eq!(clock.now(), Duration::from_secs(5));
+    }
+
+    #[test]
+    fn parses_rfc3339_timestamps_with_offsets_and_fractions() {
+        assert_eq!(parse_rfc3339("1970-01-01T00:00:00Z"), Some(Duration::ZERO));
+        assert_eq!(parse_rfc3339("2025-01-01T00:00:00Z"), Some(Duration::from_secs(1_735_689_600)));
+        assert_eq!(
+            parse_rfc3339("2025-01-01T01:00:00.5+01:00"),
+            Some(Duration::new(1_735_689_600, 500_000_000))
+        );
+        assert_eq!(parse_rfc3339("2024-02-29t12:00:00-00:30"), Some(Duration::from_secs(1_709_209_800)));
+    }
+
+    #[test]
+    fn rejects_malformed_timestamps() {
+        for value in [
+            "",
+            "2025-01-01",
+            "2025-13-01T00:00:00Z",
+            "2025-01-01T24:00:00Z",
+            "2025-01-01T00:00:00",
+            "1969-12-31T23:59:59Z",
+        ] {
+            assert_eq!(parse_rfc3339(value), None, "{:?} was parsed", value);
+        }
+    }
+}
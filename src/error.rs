@@ -1,5 +1,8 @@
 //! Error types for Qwery SDK
 
+use crate::types::Token;
+use crate::x402::PaymentRequirements;
+use std::time::Duration;
 use thiserror::Error;
 
 /// Errors that can occur when using the Qwery SDK
@@ -7,15 +10,106 @@ use thiserror::Error;
 pub enum QweryError {
     /// HTTP request failed
     #[error("HTTP request failed: {0}")]
-    RequestError(#[from] reqwest::Error),
+    RequestError(#[source] reqwest::Error),
+
+    /// Timed out connecting to the facilitator
+    #[error("Connection to facilitator timed out: {0}")]
+    ConnectTimeout(#[source] reqwest::Error),
+
+    /// Connected, but timed out waiting for the facilitator's response
+    #[error("Facilitator response timed out: {0}")]
+    ReadTimeout(#[source] reqwest::Error),
+
+    /// Facilitator refused the connection
+    #[error("Connection to facilitator refused: {0}")]
+    ConnectionRefused(#[source] reqwest::Error),
 
     /// JSON serialization/deserialization failed
     #[error("JSON error: {0}")]
     JsonError(#[from] serde_json::Error),
 
     /// API returned an error
-    #[error("API error: {0}")]
-    ApiError(String),
+    #[error("API error at {endpoint}{}: {message} (request {request_id})", fmt_payment_id(.payment_id))]
+    ApiError {
+        /// Endpoint path that failed
+        endpoint: String,
+        /// Payment the request was for, if any
+        payment_id: Option<String>,
+        /// Error body returned by the facilitator
+        message: String,
+        /// `X-Request-Id` the request was sent with
+        request_id: String,
+    },
+
+    /// Facilitator returned a success status with an empty or malformed body
+    #[error("Invalid response body from {endpoint} ({length} bytes): {message} (request {request_id})")]
+    InvalidResponse {
+        /// Endpoint path that returned the body
+        endpoint: String,
+        /// `X-Request-Id` the request was sent with
+        request_id: String,
+        /// Length of the body in bytes
+        length: usize,
+        /// What was wrong with the body
+        message: String,
+    },
+
+    /// Facilitator API major version differs from the one this SDK targets
+    #[error("Incompatible facilitator API version: SDK expects {expected}, facilitator reports {actual}")]
+    IncompatibleApiVersion {
+        /// Version this SDK was built against
+        expected: String,
+        /// Version reported by the facilitator
+        actual: String,
+    },
+
+    /// Facilitator rate-limited the request and retries were exhausted
+    #[error("Rate limited, retry after {retry_after:?}")]
+    RateLimited {
+        /// How long the facilitator asked us to wait
+        retry_after: Duration,
+    },
+
+    /// Client was shut down and accepts no new payments or settlements
+    #[error("Client is shut down")]
+    ClientClosed,
+
+    /// Operation was cancelled by the caller
+    #[error("Operation cancelled")]
+    Cancelled,
+
+    /// Deadline set with `QweryClient::with_deadline` passed before the
+    /// operation finished
+    #[error("Deadline exceeded")]
+    DeadlineExceeded,
+
+    /// Transaction wasn't confirmed before the timeout
+    #[error("Transaction {signature} not confirmed after {elapsed:?}")]
+    ConfirmationTimeout {
+        /// Signature that was being waited on
+        signature: String,
+        /// Total time spent waiting
+        elapsed: Duration,
+    },
+
+    /// Transaction landed but failed, or doesn't match the payment
+    #[error("Transaction {signature} failed verification: {status}")]
+    TransactionFailed {
+        /// Signature of the failed transaction
+        signature: String,
+        /// Verification status reported, e.g. `failed`
+        status: String,
+    },
+
+    /// Facilitator reported that the configured network isn't operational
+    ///
+    /// Returned before creating a payment when
+    /// `QweryConfig::preflight_health_check` is set.
+    #[error("Facilitator is not operational for this network: {network_status}")]
+    FacilitatorUnhealthy {
+        /// Status the facilitator reported for the network, or overall
+        network_status: String,
+    },
 
     /// Invalid configuration
     #[error("Invalid configuration: {0}")]
@@ -29,10 +123,171 @@ pub enum QweryError {
     #[error("Base64 error: {0}")]
     Base64Error(#[from] base64::DecodeError),
 
+    /// Facilitator reported that settlement did not succeed
+    #[error("Settlement failed ({status}): {}", .error.as_deref().unwrap_or("no error message"))]
+    SettlementFailed {
+        /// Payment status reported by the facilitator
+        status: String,
+        /// Error message reported by the facilitator, if any
+        error: Option<String>,
+        /// Program logs of the failed transaction, empty if unavailable
+        logs: Vec<String>,
+    },
+
+    /// Signed transaction is larger than a Solana packet allows
+    #[error("Transaction is {size} bytes, maximum is {}", crate::types::MAX_TRANSACTION_SIZE)]
+    TransactionTooLarge {
+        /// Serialized size in bytes
+        size: usize,
+    },
+
+    /// Settlement failed because a token account doesn't exist, typically the
+    /// recipient's when the payment was created with `create_recipient_ata`
+    /// set to `false`
+    #[error("Settlement of {payment_id} failed, token account missing: {message}")]
+    RecipientAccountMissing {
+        /// Payment that failed to settle
+        payment_id: String,
+        /// Error message reported by the facilitator
+        message: String,
+    },
+
+    /// Settlement failed because the transaction's blockhash had expired
+    ///
+    /// Resubmitting the same transaction can never succeed; it has to be
+    /// signed again against a fresh blockhash.
+    #[error("Settlement of {payment_id} failed, blockhash expired: {message}")]
+    BlockhashExpired {
+        /// Payment that failed to settle
+        payment_id: String,
+        /// Error message reported by the facilitator
+        message: String,
+    },
+
+    /// Payment is not in a status that allows the requested operation
+    #[error("Payment {payment_id} cannot be {action} while {status}")]
+    InvalidPaymentState {
+        /// Payment the operation was attempted on
+        payment_id: String,
+        /// Operation that was attempted
+        action: &'static str,
+        /// Current payment status
+        status: String,
+    },
+
+    /// Signed transaction still lacks valid signatures from some required signers
+    #[cfg(feature = "signing")]
+    #[error("Transaction is missing signatures from {missing:?}")]
+    IncompleteSignatures {
+        /// Required signers without a valid signature
+        missing: Vec<solana_sdk::pubkey::Pubkey>,
+    },
+
+    /// Payment belongs to a different network than the client is configured for
+    #[error("Network mismatch: client is configured for {expected}, payment is on {actual}")]
+    NetworkMismatch {
+        /// Network the client is configured for
+        expected: String,
+        /// Network the payment was created on
+        actual: String,
+    },
+
+    /// Wallet balance is lower than the payment amount
+    #[error("Insufficient {token} funds: required {required}, available {available:?}")]
+    InsufficientFunds {
+        /// Token being paid
+        token: Token,
+        /// Required amount in base units
+        required: u64,
+        /// Available balance in base units, if known
+        available: Option<u64>,
+    },
+
+    /// Payment amount is below the smallest amount worth sending
+    #[error("Amount is below the {token} minimum of {minimum} base units")]
+    AmountTooSmall {
+        /// Token being paid
+        token: Token,
+        /// Smallest accepted amount in base units
+        minimum: u64,
+    },
+
+    /// A listing matched more results than the configured limit allows
+    #[error("More than {limit} results, narrow the filter or raise the limit")]
+    TooManyResults {
+        /// Limit that was reached
+        limit: usize,
+    },
+
+    /// Facilitator answered with HTTP 402 and an x402 challenge
+    ///
+    /// Pay the challenge (for example by topping up the account it names)
+    /// and retry the call.
+    #[error("Payment required: {} base units of {} to {}", .challenge.amount, .challenge.token, .challenge.recipient)]
+    PaymentRequired {
+        /// Payment requirements parsed from the challenge
+        challenge: Box<PaymentRequirements>,
+    },
+
+    /// An x402 payment challenge could not be parsed
+    #[error("Invalid x402 challenge: {0}")]
+    X402Error(String),
+
+    /// WebSocket connection failed
+    #[error("WebSocket error: {0}")]
+    WebSocketError(String),
+
+    /// QR code generation failed
+    #[error("QR code error: {0}")]
+    QrError(String),
+
     /// Solana SDK error
     #[error("Solana error: {0}")]
     SolanaError(String),
 }
 
+impl From<reqwest::Error> for QweryError {
+    /// Classify connect and read failures so callers can tell an unreachable
+    /// facilitator from a slow one
+    fn from(error: reqwest::Error) -> Self {
+        #[cfg(not(target_arch = "wasm32"))]
+        if error.is_connect() {
+            if error.is_timeout() {
+                return QweryError::ConnectTimeout(error);
+            }
+            if is_connection_refused(&error) {
+                return QweryError::ConnectionRefused(error);
+            }
+        }
+
+        if error.is_timeout() {
+            return QweryError::ReadTimeout(error);
+        }
+        QweryError::RequestError(error)
+    }
+}
+
+/// Whether an I/O "connection refused" error is anywhere in the source chain
+#[cfg(not(target_arch = "wasm32"))]
+fn is_connection_refused(error: &reqwest::Error) -> bool {
+    let mut source = std::error::Error::source(error);
+    while let Some(err) = source {
+        if let Some(io) = err.downcast_ref::<std::io::Error>() {
+            if io.kind() == std::io::ErrorKind::ConnectionRefused {
+                return true;
+            }
+        }
+        source = err.source();
+    }
+    false
+}
+
+fn fmt_payment_id(payment_id: &Option<String>) -> String {
+    payment_id
+        .as_ref()
+        .map(|id| format!(" (payment {})", id))
+        .unwrap_or_default()
+}
+
 /// Result type for Qwery SDK operations
 pub type Result<T> = std::result::Result<T, QweryError>;
@@ -0,0 +1,115 @@
+//! Pull-based delivery of payment events for deployments without webhooks
+
+use crate::client::QweryClient;
+use crate::error::Result;
+use crate::runtime;
+use crate::types::WebhookEvent;
+use futures::stream::{self, Stream};
+use reqwest::Method;
+use serde::Deserialize;
+use std::collections::VecDeque;
+
+/// One page of events from the facilitator
+#[derive(Debug, Deserialize)]
+struct EventsPage {
+    events: Vec<WebhookEvent>,
+    /// Cursor to resume after this page, defaults to the last event ID
+    cursor: Option<String>,
+}
+
+/// State carried between polls
+struct PollState {
+    client: QweryClient,
+    cursor: Option<String>,
+    pending: VecDeque<WebhookEvent>,
+    idle_polls: u32,
+}
+
+impl QweryClient {
+    /// Stream payment events by polling the facilitator, as an alternative to
+    /// receiving webhooks
+    ///
+    /// Events are yielded in order starting after the event with ID `since`,
+    /// or from the oldest retained event if `None`. Persist the ID of the
+    /// last processed event and pass it back to resume without missing or
+    /// repeating events. When no new events are available the poll interval
+    /// backs off according to [`QweryConfig::poll`]. Errors are yielded and
+    /// polling continues, so drop the stream to stop.
+    ///
+    /// [`QweryConfig::poll`]: crate::QweryConfig::poll
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use qwery_sdk::{QweryClient, Network};
+    /// use futures::StreamExt;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let client = QweryClient::new(Network::Devnet)?;
+    ///
+    ///     let mut events = Box::pin(client.poll_events(None).await?);
+    ///     while let Some(event) = events.next().await {
+    ///         let event = event?;
+    ///         println!("{} {} -> {:?}", event.id, event.payment_id, event.status);
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn poll_events(
+        &self,
+        since: Option<String>,
+    ) -> Result<impl Stream<Item = Result<WebhookEvent>>> {
+        let state = PollState {
+            client: self.clone(),
+            cursor: since,
+            pending: VecDeque::new(),
+            idle_polls: 0,
+        };
+
+        Ok(stream::unfold(state, |mut state| async move {
+            loop {
+                if let Some(event) = state.pending.pop_front() {
+                    return Some((Ok(event), state));
+                }
+
+                if state.idle_polls > 0 {
+                    runtime::sleep(state.client.config().poll.interval(state.idle_polls - 1)).await;
+                }
+
+                match state.client.fetch_events(state.cursor.as_deref()).await {
+                    Ok(page) => {
+                        if page.events.is_empty() {
+                            state.idle_polls = state.idle_polls.saturating_add(1);
+                        } else {
+                            state.idle_polls = 0;
+                        }
+                        if let Some(cursor) = page
+                            .cursor
+                            .or_else(|| page.events.last().map(|e| e.id.clone()))
+                        {
+                            state.cursor = Some(cursor);
+                        }
+                        state.pending.extend(page.events);
+                    }
+                    Err(e) => {
+                        state.idle_polls = state.idle_polls.saturating_add(1);
+                        return Some((Err(e), state));
+                    }
+                }
+            }
+        }))
+    }
+
+    /// Fetch the next page of events after `cursor`
+    async fn fetch_events(&self, cursor: Option<&str>) -> Result<EventsPage> {
+        let mut req = self
+            .request(Method::GET, "/events")?
+            .query(&[("network", self.config().network.as_str())]);
+        if let Some(cursor) = cursor {
+            req = req.query(&[("since", cursor)]);
+        }
+
+        self.send(req, "/events", None).await
+    }
+}
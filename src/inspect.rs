@@ -0,0 +1,157 @@
+//! Decoding of payment transactions before they are signed
+
+use crate::error::{QweryError, Result};
+use crate::types::PaymentResponse;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::sanitize::Sanitize;
+use solana_sdk::system_program;
+
+/// SPL Token program
+pub(crate) const TOKEN_PROGRAM_ID: Pubkey =
+    solana_sdk::pubkey!("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA");
+/// SPL Token-2022 program
+pub(crate) const TOKEN_2022_PROGRAM_ID: Pubkey =
+    solana_sdk::pubkey!("TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb");
+/// SPL Associated Token Account program
+pub(crate) const ASSOCIATED_TOKEN_PROGRAM_ID: Pubkey =
+    solana_sdk::pubkey!("ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL");
+
+/// System program `Transfer` instruction index
+const SYSTEM_TRANSFER: u32 = 2;
+/// SPL Token `Transfer` instruction tag
+const TOKEN_TRANSFER: u8 = 3;
+/// SPL Token `TransferChecked` instruction tag
+const TOKEN_TRANSFER_CHECKED: u8 = 12;
+
+/// Human-checkable summary of a payment transaction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransactionSummary {
+    /// Account paying the transaction fee
+    pub fee_payer: Pubkey,
+    /// SOL and SPL token transfers in the transaction
+    pub transfers: Vec<TransferSummary>,
+    /// Every instruction that isn't a recognised transfer
+    pub other_instructions: Vec<InstructionSummary>,
+}
+
+/// A single SOL or SPL token transfer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransferSummary {
+    /// Amount in base units (lamports for SOL)
+    pub amount: u64,
+    /// Source account (wallet for SOL, token account for SPL)
+    pub source: Pubkey,
+    /// Destination account (wallet for SOL, token account for SPL)
+    pub destination: Pubkey,
+    /// Account authorizing the transfer
+    pub authority: Pubkey,
+    /// Token mint, `None` for SOL or when the instruction doesn't name it
+    pub mint: Option<Pubkey>,
+}
+
+/// An instruction that isn't a recognised transfer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InstructionSummary {
+    /// Program the instruction invokes
+    pub program_id: Pubkey,
+    /// Accounts passed to the instruction
+    pub accounts: Vec<Pubkey>,
+    /// Raw instruction data
+    pub data: Vec<u8>,
+}
+
+impl PaymentResponse {
+    /// Decode the unsigned transaction and summarise what it does
+    ///
+    /// Use this to check that a payment transfers the expected amount to the
+    /// expected recipient, and nothing else, before signing it. A malformed
+    /// transaction fails with [`QweryError::SolanaError`].
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # fn example(payment: qwery_sdk::PaymentResponse) -> qwery_sdk::error::Result<()> {
+    /// let summary = payment.inspect_transaction()?;
+    ///
+    /// assert_eq!(summary.transfers.len(), 1);
+    /// assert_eq!(summary.transfers[0].amount, 10_000_000);
+    /// assert!(summary.other_instructions.is_empty());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn inspect_transaction(&self) -> Result<TransactionSummary> {
+        let transaction = self.decode_transaction()?;
+        let message = &transaction.message;
+        // Account indices come from the facilitator and are checked before use
+        message
+            .sanitize()
+            .map_err(|e| QweryError::SolanaError(format!("malformed transaction: {}", e)))?;
+
+        let mut transfers = Vec::new();
+        let mut other_instructions = Vec::new();
+
+        for ix in &message.instructions {
+            let program_id = message.account_keys[ix.program_id_index as usize];
+            let accounts: Vec<Pubkey> = ix
+                .accounts
+                .iter()
+                .map(|&i| message.account_keys[i as usize])
+                .collect();
+
+            match parse_transfer(&program_id, &accounts, &ix.data) {
+                Some(transfer) => transfers.push(transfer),
+                None => other_instructions.push(InstructionSummary {
+                    program_id,
+                    accounts,
+                    data: ix.data.clone(),
+                }),
+            }
+        }
+
+        Ok(TransactionSummary {
+            fee_payer: message.account_keys.first().copied().unwrap_or_default(),
+            transfers,
+            other_instructions,
+        })
+    }
+}
+
+/// Parse a System or SPL Token transfer instruction
+fn parse_transfer(program_id: &Pubkey, accounts: &[Pubkey], data: &[u8]) -> Option<TransferSummary> {
+    if *program_id == system_program::id() {
+        let index = u32::from_le_bytes(data.get(..4)?.try_into().ok()?);
+        if index != SYSTEM_TRANSFER || accounts.len() < 2 {
+            return None;
+        }
+        return Some(TransferSummary {
+            amount: u64::from_le_bytes(data.get(4..12)?.try_into().ok()?),
+            source: accounts[0],
+            destination: accounts[1],
+            authority: accounts[0],
+            mint: None,
+        });
+    }
+
+    if *program_id != TOKEN_PROGRAM_ID && *program_id != TOKEN_2022_PROGRAM_ID {
+        return None;
+    }
+
+    let amount = u64::from_le_bytes(data.get(1..9)?.try_into().ok()?);
+    match *data.first()? {
+        TOKEN_TRANSFER if accounts.len() >= 3 => Some(TransferSummary {
+            amount,
+            source: accounts[0],
+            destination: accounts[1],
+            authority: accounts[2],
+            mint: None,
+        }),
+        TOKEN_TRANSFER_CHECKED if accounts.len() >= 4 => Some(TransferSummary {
+            amount,
+            source: accounts[0],
+            destination: accounts[2],
+            authority: accounts[3],
+            mint: Some(accounts[1]),
+        }),
+        _ => None,
+    }
+}
@@ -0,0 +1,89 @@
+//! Background health pings that keep the facilitator and connection warm
+
+use crate::client::QweryClient;
+use crate::error::{QweryError, Result};
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+use tokio_util::sync::{CancellationToken, DropGuard};
+
+/// Stops the keepalive task started by [`QweryClient::start_keepalive`] when dropped
+#[derive(Debug)]
+pub struct KeepaliveHandle {
+    _guard: DropGuard,
+}
+
+impl KeepaliveHandle {
+    /// Stop pinging; same as dropping the handle
+    pub fn stop(self) {}
+}
+
+impl QweryClient {
+    /// Ping the facilitator's health endpoint every `interval` until the
+    /// returned handle is dropped
+    ///
+    /// Keeps a serverless facilitator and the pooled connection warm so the
+    /// first payment after a quiet period isn't slowed by a cold start. Each
+    /// wait is jittered by up to ±10% so many clients don't ping in step.
+    /// Failed pings are ignored. The task also stops once the client is
+    /// [shut down](QweryClient::shutdown), and isn't started at all if it
+    /// already has been, in which case [`QweryError::ClientClosed`] is
+    /// returned. Must be called from within a tokio runtime; not available
+    /// on `wasm32`.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use qwery_sdk::{QweryClient, Network};
+    /// use std::time::Duration;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let client = QweryClient::new(Network::Mainnet)?;
+    ///     let _keepalive = client.start_keepalive(Duration::from_secs(60))?;
+    ///
+    ///     // Serve requests; pings stop when `_keepalive` is dropped
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn start_keepalive(&self, interval: Duration) -> Result<KeepaliveHandle> {
+        if self.is_closed() {
+            return Err(QweryError::ClientClosed);
+        }
+
+        let token = CancellationToken::new();
+        let cancelled = token.clone();
+        let client = self.clone();
+
+        tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    _ = cancelled.cancelled() => return,
+                    _ = tokio::time::sleep(jittered(interval)) => {}
+                }
+                if client.is_closed() {
+                    return;
+                }
+                tokio::select! {
+                    _ = cancelled.cancelled() => return,
+                    result = client.health() => {
+                        if let Err(e) = result {
+                            tracing::debug!(error = %e, "keepalive ping failed");
+                        }
+                    }
+                }
+            }
+        });
+
+        Ok(KeepaliveHandle {
+            _guard: token.drop_guard(),
+        })
+    }
+}
+
+/// `interval` scaled by a random factor between 0.9 and 1.1
+fn jittered(interval: Duration) -> Duration {
+    let random = RandomState::new().build_hasher().finish();
+    let factor = 0.9 + (random % 2001) as f64 / 10_000.0;
+    interval.mul_f64(factor)
+}
@@ -23,6 +23,7 @@
 //!         token: "SOL".to_string(),
 //!         recipient: "recipient_wallet_address".to_string(),
 //!         metadata: None,
+//!         ..Default::default()
 //!     }).await?;
 //!
 //!     println!("Payment ID: {}", payment.payment_id);
@@ -30,10 +31,43 @@
 //! }
 //! ```
 
+pub mod api;
+pub mod auth;
 pub mod client;
+pub mod clock;
 pub mod types;
 pub mod error;
+#[cfg(feature = "signing")]
+pub mod inspect;
+pub mod metrics;
+#[cfg(feature = "signing")]
+pub mod signer;
+#[cfg(feature = "signing")]
+pub mod tracker;
+pub mod uri;
+pub mod x402;
+#[cfg(all(feature = "signing", not(target_arch = "wasm32")))]
+mod rpc;
+mod cache;
+mod events;
+#[cfg(not(target_arch = "wasm32"))]
+mod keepalive;
+mod runtime;
+mod shutdown;
+#[cfg(feature = "websocket")]
+mod subscribe;
+mod unsettled;
+#[cfg(all(test, feature = "signing", not(target_arch = "wasm32")))]
+mod mock_facilitator;
 
-pub use client::QweryClient;
+pub use client::{
+    QweryClient, QweryClientBuilder, API_VERSION, API_VERSION_HEADER, DRY_RUN_SIGNATURE_PREFIX,
+    REQUEST_ID_HEADER,
+};
 pub use types::*;
 pub use error::QweryError;
+pub use api::QweryApi;
+#[cfg(not(target_arch = "wasm32"))]
+pub use keepalive::KeepaliveHandle;
+pub use tokio_util::sync::CancellationToken;
+pub use reqwest::Method;
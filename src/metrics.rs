@@ -0,0 +1,49 @@
+//! Observability hooks for payment lifecycle events
+
+use crate::error::QweryError;
+use crate::types::Token;
+use std::fmt::Debug;
+use std::time::Duration;
+
+/// Receives payment lifecycle events from a [`QweryClient`]
+///
+/// Every method has a no-op default, so implementors only override the
+/// events they care about. Set an observer via [`QweryConfig::metrics`].
+///
+/// [`QweryClient`]: crate::QweryClient
+/// [`QweryConfig::metrics`]: crate::QweryConfig::metrics
+///
+/// # Example
+///
+/// ```rust
+/// use qwery_sdk::{QweryConfig, Token};
+/// use qwery_sdk::metrics::MetricsObserver;
+/// use std::sync::atomic::{AtomicU64, Ordering};
+/// use std::sync::Arc;
+///
+/// #[derive(Debug, Default)]
+/// struct Counters {
+///     created: AtomicU64,
+/// }
+///
+/// impl MetricsObserver for Counters {
+///     fn on_payment_created(&self, _id: &str, _token: Token, _amount_units: u64) {
+///         self.created.fetch_add(1, Ordering::Relaxed);
+///     }
+/// }
+///
+/// let config = QweryConfig {
+///     metrics: Some(Arc::new(Counters::default())),
+///     ..Default::default()
+/// };
+/// ```
+pub trait MetricsObserver: Debug + Send + Sync {
+    /// A payment was created
+    fn on_payment_created(&self, _id: &str, _token: Token, _amount_units: u64) {}
+
+    /// Creating a payment failed
+    fn on_payment_failed(&self, _error: &QweryError) {}
+
+    /// A settlement attempt finished, successfully or not
+    fn on_settlement(&self, _id: &str, _success: bool, _latency: Duration) {}
+}
@@ -0,0 +1,721 @@
+//! On-chain helpers that talk to a Solana RPC node directly
+
+use crate::client::QweryClient;
+use crate::error::{QweryError, Result};
+use crate::inspect::TOKEN_PROGRAM_ID;
+use crate::types::{
+    CommitmentLevel, ExpectedTransfer, Network, PaymentResponse, RecipientCheck, Token,
+    VerifyResponse, FINALIZED_CONFIRMATIONS,
+};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_config::RpcTransactionConfig;
+use solana_sdk::{
+    commitment_config::CommitmentConfig,
+    hash::Hash,
+    pubkey::Pubkey,
+    signature::Signature,
+    transaction::Transaction,
+};
+use solana_transaction_status::{
+    TransactionConfirmationStatus, UiLoadedAddresses, UiTransactionEncoding,
+    UiTransactionStatusMeta, UiTransactionTokenBalance,
+};
+use std::fmt;
+use std::str::FromStr;
+use std::sync::{Arc, OnceLock};
+
+/// RPC client for the configured `rpc_url`, built on first use
+///
+/// Shared between clones of a [`QweryClient`] so they reuse its connections.
+#[derive(Default)]
+pub(crate) struct LazyRpcClient(OnceLock<Arc<RpcClient>>);
+
+impl fmt::Debug for LazyRpcClient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("LazyRpcClient")
+            .field(&self.0.get().map(|rpc| rpc.url()))
+            .finish()
+    }
+}
+
+/// SPL Memo program (v2)
+const MEMO_PROGRAM_ID: &str = "MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr";
+/// SPL Memo program (v1, legacy)
+const MEMO_V1_PROGRAM_ID: &str = "Memo1UhkJRfHyvLMcVucJwxXeuD728EqVDDwQDxFMNo";
+
+impl QweryClient {
+    /// Verify that a settled transaction carries the expected memo or reference
+    ///
+    /// Fetches the transaction from `rpc_url` and returns `true` if any Memo
+    /// program instruction matches `expected_memo` exactly, or, when
+    /// `expected_memo` is a valid public key, if the transaction references
+    /// that account (Solana Pay reference convention). A transaction that
+    /// landed but failed never matches.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use qwery_sdk::{QweryClient, Network};
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let client = QweryClient::new(Network::Mainnet)?;
+    ///
+    ///     let matches = client.verify_payment_with_memo(
+    ///         "transaction_signature",
+    ///         "INV-2024-0042",
+    ///         "https://api.mainnet-beta.solana.com",
+    ///     ).await?;
+    ///
+    ///     println!("Memo matches: {}", matches);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn verify_payment_with_memo(
+        &self,
+        signature: &str,
+        expected_memo: &str,
+        rpc_url: &str,
+    ) -> Result<bool> {
+        let signature = Signature::from_str(signature)
+            .map_err(|e| QweryError::ConfigError(format!("invalid signature: {}", e)))?;
+
+        let rpc = RpcClient::new(rpc_url.to_string());
+        let confirmed = rpc
+            .get_transaction_with_config(
+                &signature,
+                RpcTransactionConfig {
+                    encoding: Some(UiTransactionEncoding::Base64),
+                    commitment: Some(CommitmentConfig::confirmed()),
+                    max_supported_transaction_version: Some(0),
+                },
+            )
+            .await
+            .map_err(|e| QweryError::SolanaError(e.to_string()))?;
+
+        let transaction = confirmed
+            .transaction
+            .transaction
+            .decode()
+            .ok_or_else(|| QweryError::SolanaError("failed to decode transaction".to_string()))?;
+        let meta = confirmed
+            .transaction
+            .meta
+            .ok_or_else(|| QweryError::SolanaError("transaction has no status meta".to_string()))?;
+        if meta.err.is_some() {
+            return Ok(false);
+        }
+
+        let memo_programs = [
+            Pubkey::from_str(MEMO_PROGRAM_ID).expect("valid memo program id"),
+            Pubkey::from_str(MEMO_V1_PROGRAM_ID).expect("valid memo program id"),
+        ];
+        let account_keys = transaction.message.static_account_keys();
+
+        let memo_matches = transaction.message.instructions().iter().any(|ix| {
+            account_keys
+                .get(ix.program_id_index as usize)
+                .is_some_and(|program| memo_programs.contains(program))
+                && ix.data == expected_memo.as_bytes()
+        });
+        if memo_matches {
+            return Ok(true);
+        }
+
+        // Fall back to the Solana Pay reference convention
+        let Ok(reference) = Pubkey::from_str(expected_memo) else {
+            return Ok(false);
+        };
+        let loaded = Option::<&UiLoadedAddresses>::from(meta.loaded_addresses.as_ref());
+        let reference_matches = account_keys.contains(&reference)
+            || loaded.is_some_and(|loaded| {
+                loaded
+                    .writable
+                    .iter()
+                    .chain(&loaded.readonly)
+                    .any(|key| Pubkey::from_str(key).ok() == Some(reference))
+            });
+
+        Ok(reference_matches)
+    }
+
+    /// Verify a payment against the chain instead of asking the facilitator
+    ///
+    /// Looks `signature` up through `rpc_url` and reports it as verified only
+    /// if it landed without error and `expected.recipient` received at least
+    /// `expected.amount` of `expected.token`, judged from the balance changes
+    /// the transaction recorded. `status` is the transaction's commitment
+    /// level, or `not_found`, `processed`, `failed` or `mismatch` when it
+    /// isn't verified. A `processed` transaction can't be fetched yet, so it
+    /// is reported unverified until it is confirmed.
+    ///
+    /// Returns [`QweryError::ConfigError`] if `expected.token` has no mint on
+    /// the configured network.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use qwery_sdk::{ExpectedTransfer, QweryClient, QweryConfig, Network};
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let client = QweryClient::with_config(QweryConfig {
+    ///         rpc_url: Some(Network::Mainnet.default_rpc_url().to_string()),
+    ///         ..Default::default()
+    ///     })?;
+    ///
+    ///     let payment = client.get_payment("payment_id").await?;
+    ///     let expected = ExpectedTransfer::from_payment(&payment)?;
+    ///     let result = client.verify_onchain("transaction_signature", &expected).await?;
+    ///     println!("Verified: {} ({})", result.verified, result.status);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn verify_onchain(
+        &self,
+        signature: &str,
+        expected: &ExpectedTransfer,
+    ) -> Result<VerifyResponse> {
+        let parsed = Signature::from_str(signature)
+            .map_err(|e| QweryError::ConfigError(format!("invalid signature: {}", e)))?;
+        let network = self.config().network;
+        let mint = match expected.token {
+            Token::Sol => None,
+            token => Some(token.mint_address(network).ok_or_else(|| {
+                QweryError::ConfigError(format!("{} has no mint on {}", token, network))
+            })?),
+        };
+        let rpc = self.rpc_client()?;
+
+        let unverified = |status: &str, confirmations: Option<u64>| VerifyResponse {
+            verified: false,
+            status: status.to_string(),
+            confirmations,
+            signature: Some(signature.to_string()),
+            commitment: None,
+            network: None,
+            time_to_finality: None,
+        };
+
+        let status = rpc
+            .get_signature_statuses_with_history(&[parsed])
+            .await
+            .map_err(|e| QweryError::SolanaError(e.to_string()))?
+            .value
+            .into_iter()
+            .next()
+            .flatten();
+        let Some(status) = status else {
+            return Ok(unverified("not_found", None));
+        };
+
+        // Rooted transactions report no confirmation count
+        let confirmations = status
+            .confirmations
+            .map_or(FINALIZED_CONFIRMATIONS, |c| c as u64);
+        if status.err.is_some() {
+            return Ok(unverified("failed", Some(confirmations)));
+        }
+        // Transactions are only fetchable once confirmed
+        if status.confirmation_status == Some(TransactionConfirmationStatus::Processed) {
+            return Ok(unverified("processed", Some(confirmations)));
+        }
+
+        let confirmed = rpc
+            .get_transaction_with_config(
+                &parsed,
+                RpcTransactionConfig {
+                    encoding: Some(UiTransactionEncoding::Base64),
+                    commitment: Some(CommitmentConfig::confirmed()),
+                    max_supported_transaction_version: Some(0),
+                },
+            )
+            .await
+            .map_err(|e| QweryError::SolanaError(e.to_string()))?;
+        let transaction = confirmed
+            .transaction
+            .transaction
+            .decode()
+            .ok_or_else(|| QweryError::SolanaError("failed to decode transaction".to_string()))?;
+        let meta = confirmed
+            .transaction
+            .meta
+            .ok_or_else(|| QweryError::SolanaError("transaction has no status meta".to_string()))?;
+
+        let mut account_keys: Vec<Pubkey> = transaction.message.static_account_keys().to_vec();
+        if let Some(loaded) = Option::<&UiLoadedAddresses>::from(meta.loaded_addresses.as_ref()) {
+            account_keys.extend(
+                loaded
+                    .writable
+                    .iter()
+                    .chain(&loaded.readonly)
+                    .filter_map(|key| Pubkey::from_str(key).ok()),
+            );
+        }
+
+        let received = match mint {
+            None => lamports_received(&meta, &account_keys, &expected.recipient),
+            Some(mint) => tokens_received(&meta, &mint, &expected.recipient),
+        };
+        if received < expected.amount as i128 {
+            return Ok(unverified("mismatch", Some(confirmations)));
+        }
+
+        let (commitment, status) = match status.confirmation_status {
+            Some(TransactionConfirmationStatus::Confirmed) => (CommitmentLevel::Confirmed, "confirmed"),
+            _ => (CommitmentLevel::Finalized, "finalized"),
+        };
+
+        Ok(VerifyResponse {
+            verified: true,
+            status: status.to_string(),
+            confirmations: Some(confirmations),
+            signature: Some(signature.to_string()),
+            commitment: Some(commitment),
+            network: None,
+            time_to_finality: None,
+        })
+    }
+
+    /// Request a devnet airdrop of `lamports` to `address`
+    ///
+    /// Uses `rpc_url` if configured (e.g. a local test validator), otherwise
+    /// the network's [public endpoint](Network::default_rpc_url). Returns [`QweryError::ConfigError`] when the
+    /// client is configured for mainnet.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use qwery_sdk::{QweryClient, Network};
+    /// use solana_sdk::signature::{Keypair, Signer};
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let client = QweryClient::new(Network::Devnet)?;
+    ///     let keypair = Keypair::new();
+    ///
+    ///     let signature = client.request_airdrop(&keypair.pubkey(), 1_000_000_000).await?;
+    ///     println!("Airdrop: {}", signature);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn request_airdrop(&self, address: &Pubkey, lamports: u64) -> Result<Signature> {
+        let config = self.config();
+        if config.network == Network::Mainnet {
+            return Err(QweryError::ConfigError(
+                "airdrops are not available on mainnet".to_string(),
+            ));
+        }
+
+        let rpc = match config.rpc_url {
+            Some(_) => self.rpc_client()?,
+            None => Arc::new(RpcClient::new(config.network.default_rpc_url().to_string())),
+        };
+        rpc.request_airdrop(address, lamports)
+            .await
+            .map_err(|e| QweryError::SolanaError(format!("airdrop failed: {}", e)))
+    }
+
+    /// Fetch the latest blockhash and the last block height it is valid for
+    ///
+    /// Requires `rpc_url`. Pass the blockhash to
+    /// [`PaymentRequest::recent_blockhash`](crate::PaymentRequest::recent_blockhash)
+    /// to reuse it across several payments; they stay valid until the chain
+    /// passes the returned block height.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use qwery_sdk::{QweryClient, QweryConfig, PaymentRequest};
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let client = QweryClient::with_config(QweryConfig {
+    ///         rpc_url: Some("https://api.mainnet-beta.solana.com".to_string()),
+    ///         ..Default::default()
+    ///     })?;
+    ///
+    ///     let (blockhash, last_valid_block_height) = client.get_latest_blockhash().await?;
+    ///     println!("{} valid until block {}", blockhash, last_valid_block_height);
+    ///
+    ///     let request = PaymentRequest::builder()
+    ///         .amount(1.0)
+    ///         .token("USDC")
+    ///         .recipient("recipient_address")
+    ///         .recent_blockhash(blockhash)
+    ///         .build()?;
+    ///     let payment = client.create_payment(request).await?;
+    ///     println!("Payment ID: {}", payment.payment_id);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn get_latest_blockhash(&self) -> Result<(Hash, u64)> {
+        self.rpc_client()?
+            .get_latest_blockhash_with_commitment(CommitmentConfig::confirmed())
+            .await
+            .map_err(|e| QweryError::SolanaError(format!("failed to fetch blockhash: {}", e)))
+    }
+
+    /// Check whether `recipient` can receive `token` before creating a payment
+    ///
+    /// Looks up the recipient and, for SPL tokens, its associated token
+    /// account on the configured `rpc_url`. A missing token account doesn't
+    /// stop the payment, since the facilitator creates it unless
+    /// [`PaymentRequest::create_recipient_ata`] is `false`; a frozen one
+    /// does. Returns [`QweryError::ConfigError`] for tokens without a mint on
+    /// the configured network.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use qwery_sdk::{QweryClient, QweryConfig, Token};
+    /// use solana_sdk::pubkey::Pubkey;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let client = QweryClient::with_config(QweryConfig {
+    ///         rpc_url: Some("https://api.mainnet-beta.solana.com".to_string()),
+    ///         ..Default::default()
+    ///     })?;
+    ///
+    ///     let check = client.check_recipient(&Pubkey::new_unique(), Token::Usdc).await?;
+    ///     if !check.can_receive {
+    ///         println!("Recipient can't receive USDC: {:?}", check);
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    ///
+    /// [`PaymentRequest::create_recipient_ata`]: crate::PaymentRequest::create_recipient_ata
+    pub async fn check_recipient(&self, recipient: &Pubkey, token: Token) -> Result<RecipientCheck> {
+        let rpc = self.rpc_client()?;
+        let ata = match token {
+            Token::Sol => None,
+            _ => Some(self.resolve_recipient_ata(recipient, token)?),
+        };
+
+        let addresses: Vec<Pubkey> = std::iter::once(*recipient).chain(ata).collect();
+        let accounts = rpc.get_multiple_accounts(&addresses).await.map_err(|e| {
+            QweryError::SolanaError(format!("failed to fetch recipient accounts: {}", e))
+        })?;
+
+        // Funds sent to a program, mint or token account as if it were a
+        // wallet are lost
+        let address_valid = accounts[0]
+            .as_ref()
+            .is_none_or(|account| !account.executable && account.owner != TOKEN_PROGRAM_ID);
+
+        let token_account = accounts.get(1).and_then(Option::as_ref);
+        let ata_exists = token_account.is_some();
+        // Byte 108 of an SPL token account is its state, 2 meaning frozen
+        let token_account_frozen =
+            token_account.is_some_and(|account| account.data.get(108) == Some(&2));
+
+        Ok(RecipientCheck {
+            address_valid,
+            ata_exists,
+            token_account_frozen,
+            can_receive: address_valid && !token_account_frozen,
+        })
+    }
+
+    /// Whether the payment's transaction can still land on chain
+    ///
+    /// Compares [`PaymentResponse::last_valid_block_height`] with the current
+    /// block height on `rpc_url`, or asks the node about the transaction's
+    /// blockhash if the facilitator didn't report a height. Once this is
+    /// `false` the payment has to be created again to be paid.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use qwery_sdk::{QweryClient, PaymentResponse};
+    ///
+    /// async fn still_payable(client: &QweryClient, payment: &PaymentResponse) -> bool {
+    ///     client.is_blockhash_still_valid(payment).await.unwrap_or(false)
+    /// }
+    /// ```
+    pub async fn is_blockhash_still_valid(&self, payment: &PaymentResponse) -> Result<bool> {
+        let rpc = self.rpc_client()?;
+
+        match payment.last_valid_block_height {
+            Some(last_valid_block_height) => {
+                let height = rpc
+                    .get_block_height_with_commitment(CommitmentConfig::confirmed())
+                    .await
+                    .map_err(|e| {
+                        QweryError::SolanaError(format!("failed to fetch block height: {}", e))
+                    })?;
+                Ok(height <= last_valid_block_height)
+            }
+            None => {
+                let transaction = payment.decode_transaction()?;
+                rpc.is_blockhash_valid(
+                    &transaction.message.recent_blockhash,
+                    CommitmentConfig::confirmed(),
+                )
+                .await
+                .map_err(|e| {
+                    QweryError::SolanaError(format!("failed to check blockhash validity: {}", e))
+                })
+            }
+        }
+    }
+
+    /// Find and verify the payment whose transfer carries `reference`
+    ///
+    /// Scans the transactions that mention the reference key on `rpc_url`,
+    /// for payments created with [`PaymentRequest::reference`], and verifies
+    /// the most recent successful one with the facilitator, or failing that
+    /// the most recent one. Returns `None` if no transaction references the
+    /// key yet.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use qwery_sdk::{QweryClient, QweryConfig, PaymentRequest};
+    /// use solana_sdk::pubkey::Pubkey;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let client = QweryClient::with_config(QweryConfig {
+    ///         rpc_url: Some("https://api.mainnet-beta.solana.com".to_string()),
+    ///         ..Default::default()
+    ///     })?;
+    ///
+    ///     // Stored alongside the order when the payment was created
+    ///     let reference = Pubkey::new_unique();
+    ///     let request = PaymentRequest::builder()
+    ///         .amount(5.0)
+    ///         .token("USDC")
+    ///         .recipient("recipient_address")
+    ///         .reference(reference)
+    ///         .build()?;
+    ///     client.create_payment(request).await?;
+    ///
+    ///     match client.find_by_reference(&reference).await? {
+    ///         Some(result) => println!("Paid: {}", result.verified),
+    ///         None => println!("Not paid yet"),
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    ///
+    /// [`PaymentRequest::reference`]: crate::PaymentRequest::reference
+    pub async fn find_by_reference(&self, reference: &Pubkey) -> Result<Option<VerifyResponse>> {
+        let signatures = self
+            .rpc_client()?
+            .get_signatures_for_address(reference)
+            .await
+            .map_err(|e| {
+                QweryError::SolanaError(format!("failed to fetch signatures for reference: {}", e))
+            })?;
+
+        // Listed newest first
+        let found = signatures
+            .iter()
+            .find(|status| status.err.is_none())
+            .or_else(|| signatures.first());
+        match found {
+            Some(status) => self.verify_payment(&status.signature).await.map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Whether an account exists at `address`
+    pub(crate) async fn account_exists(&self, address: &Pubkey) -> Result<bool> {
+        let account = self
+            .rpc_client()?
+            .get_account_with_commitment(address, CommitmentConfig::confirmed())
+            .await
+            .map_err(|e| QweryError::SolanaError(format!("failed to fetch account: {}", e)))?;
+        Ok(account.value.is_some())
+    }
+
+    /// Replace the transaction's blockhash if it has expired
+    ///
+    /// Only an unsigned transaction can be refreshed: a signature already on
+    /// it, e.g. the facilitator's as fee payer, would no longer match the
+    /// message and can't be recreated here, so this fails with
+    /// [`QweryError::SigningError`] naming the signer instead of dropping it.
+    pub(crate) async fn refresh_blockhash(&self, transaction: &mut Transaction) -> Result<()> {
+        let rpc = self.rpc_client()?;
+
+        let valid = rpc
+            .is_blockhash_valid(
+                &transaction.message.recent_blockhash,
+                CommitmentConfig::processed(),
+            )
+            .await
+            .map_err(|e| {
+                QweryError::SolanaError(format!("failed to check blockhash validity: {}", e))
+            })?;
+        if valid {
+            return Ok(());
+        }
+
+        let presigned = transaction
+            .signatures
+            .iter()
+            .zip(&transaction.message.account_keys)
+            .find(|(signature, _)| **signature != Signature::default());
+        if let Some((_, signer)) = presigned {
+            return Err(QweryError::SigningError(format!(
+                "blockhash expired but the transaction is already signed by {}; \
+                 fetch the payment again for a rebuilt transaction",
+                signer
+            )));
+        }
+
+        let blockhash = rpc.get_latest_blockhash().await.map_err(|e| {
+            QweryError::SolanaError(format!("failed to refresh blockhash: {}", e))
+        })?;
+
+        transaction.message.recent_blockhash = blockhash;
+        transaction.signatures =
+            vec![Signature::default(); transaction.message.header.num_required_signatures as usize];
+        Ok(())
+    }
+
+    /// Program logs of a landed transaction, empty if they can't be fetched
+    ///
+    /// Used to explain settlement failures, so it never fails itself.
+    pub(crate) async fn transaction_logs(&self, signature: &str) -> Vec<String> {
+        let (Ok(rpc), Ok(signature)) = (self.rpc_client(), Signature::from_str(signature)) else {
+            return Vec::new();
+        };
+
+        let confirmed = rpc
+            .get_transaction_with_config(
+                &signature,
+                RpcTransactionConfig {
+                    encoding: Some(UiTransactionEncoding::Base64),
+                    commitment: Some(CommitmentConfig::confirmed()),
+                    max_supported_transaction_version: Some(0),
+                },
+            )
+            .await;
+
+        confirmed
+            .ok()
+            .and_then(|confirmed| confirmed.transaction.meta)
+            .and_then(|meta| Option::from(meta.log_messages))
+            .unwrap_or_default()
+    }
+
+    /// RPC client for the configured `rpc_url`, created on first use
+    pub(crate) fn rpc_client(&self) -> Result<Arc<RpcClient>> {
+        let rpc_url = self
+            .config()
+            .rpc_url
+            .as_ref()
+            .ok_or_else(|| QweryError::ConfigError("rpc_url not configured".to_string()))?;
+        Ok(self
+            .lazy_rpc()
+            .0
+            .get_or_init(|| Arc::new(RpcClient::new(rpc_url.clone())))
+            .clone())
+    }
+}
+
+/// Net lamports `recipient` gained in a transaction
+fn lamports_received(
+    meta: &UiTransactionStatusMeta,
+    account_keys: &[Pubkey],
+    recipient: &Pubkey,
+) -> i128 {
+    let Some(index) = account_keys.iter().position(|key| key == recipient) else {
+        return 0;
+    };
+    let pre = meta.pre_balances.get(index).copied().unwrap_or(0);
+    let post = meta.post_balances.get(index).copied().unwrap_or(0);
+    post as i128 - pre as i128
+}
+
+/// Net amount of `mint`, in base units, token accounts owned by `recipient`
+/// gained in a transaction
+fn tokens_received(meta: &UiTransactionStatusMeta, mint: &Pubkey, recipient: &Pubkey) -> i128 {
+    let mint = mint.to_string();
+    let recipient = recipient.to_string();
+    let total = |balances: Option<&Vec<UiTransactionTokenBalance>>| {
+        balances
+            .into_iter()
+            .flatten()
+            .filter(|balance| {
+                balance.mint == mint
+                    && Option::<&String>::from(balance.owner.as_ref()) == Some(&recipient)
+            })
+            .filter_map(|balance| balance.ui_token_amount.amount.parse::<i128>().ok())
+            .sum::<i128>()
+    };
+
+    total(meta.post_token_balances.as_ref().into()) - total(meta.pre_token_balances.as_ref().into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_facilitator::MockFacilitator;
+    use crate::types::QweryConfig;
+    use solana_sdk::signature::{Keypair, Signer};
+
+    /// An RPC node on which every blockhash has expired and `latest` is current
+    async fn expired_rpc(latest: Hash) -> MockFacilitator {
+        MockFacilitator::start(move |request| {
+            let result = if request.body.contains("getVersion") {
+                r#"{"solana-core":"1.18.26","feature-set":0}"#.to_string()
+            } else if request.body.contains("isBlockhashValid") {
+                r#"{"context":{"slot":1},"value":false}"#.to_string()
+            } else {
+                format!(
+                    r#"{{"context":{{"slot":1}},"value":{{"blockhash":"{}","lastValidBlockHeight":100}}}}"#,
+                    latest
+                )
+            };
+            (200, format!(r#"{{"jsonrpc":"2.0","result":{},"id":1}}"#, result))
+        })
+        .await
+    }
+
+    fn client_for(mock: &MockFacilitator) -> QweryClient {
+        QweryClient::with_config(QweryConfig {
+            rpc_url: Some(mock.url.clone()),
+            ..Default::default()
+        })
+        .unwrap()
+    }
+
+    fn sponsored_transfer(fee_payer: &Pubkey, payer: &Pubkey) -> Transaction {
+        let transfer = solana_sdk::system_instruction::transfer(payer, &Pubkey::new_unique(), 1_000);
+        Transaction::new_with_payer(&[transfer], Some(fee_payer))
+    }
+
+    #[tokio::test]
+    async fn refresh_blockhash_replaces_an_expired_blockhash() {
+        let latest = Hash::new_unique();
+        let mock = expired_rpc(latest).await;
+        let mut transaction = sponsored_transfer(&Pubkey::new_unique(), &Pubkey::new_unique());
+
+        client_for(&mock).refresh_blockhash(&mut transaction).await.unwrap();
+        assert_eq!(transaction.message.recent_blockhash, latest);
+    }
+
+    #[tokio::test]
+    async fn refresh_blockhash_keeps_a_presigned_transaction() {
+        let mock = expired_rpc(Hash::new_unique()).await;
+        let fee_payer = Keypair::new();
+        let mut transaction = sponsored_transfer(&fee_payer.pubkey(), &Pubkey::new_unique());
+        let stale = Hash::new_unique();
+        transaction.partial_sign(&[&fee_payer], stale);
+
+        let result = client_for(&mock).refresh_blockhash(&mut transaction).await;
+        match result {
+            Err(QweryError::SigningError(message)) => {
+                assert!(message.contains(&fee_payer.pubkey().to_string()), "{}", message)
+            }
+            other => panic!("expected SigningError, got {:?}", other),
+        }
+        assert_eq!(transaction.message.recent_blockhash, stale);
+        assert_ne!(transaction.signatures[0], Signature::default());
+    }
+}
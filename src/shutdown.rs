@@ -0,0 +1,71 @@
+//! Tracking of in-flight operations so a client can shut down cleanly
+
+use crate::error::{QweryError, Result};
+use crate::runtime;
+use futures::future::{self, Either};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::Duration;
+use tokio::sync::Notify;
+
+/// Whether the client is closed, and how many operations are still running
+#[derive(Debug, Default)]
+pub(crate) struct Lifecycle {
+    closed: AtomicBool,
+    in_flight: AtomicUsize,
+    drained: Notify,
+}
+
+impl Lifecycle {
+    /// Register a new operation, failing if the client has been shut down
+    pub(crate) fn enter(&self) -> Result<InFlight<'_>> {
+        self.in_flight.fetch_add(1, Ordering::SeqCst);
+        if self.closed.load(Ordering::SeqCst) {
+            self.leave();
+            return Err(QweryError::ClientClosed);
+        }
+        Ok(InFlight(self))
+    }
+
+    /// Whether [`close`](Self::close) has been called
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::SeqCst)
+    }
+
+    /// Refuse new operations and wait up to `timeout` for running ones
+    ///
+    /// Returns whether every operation finished in time.
+    pub(crate) async fn close(&self, timeout: Duration) -> bool {
+        self.closed.store(true, Ordering::SeqCst);
+
+        let drained = async {
+            loop {
+                let notified = self.drained.notified();
+                if self.in_flight.load(Ordering::SeqCst) == 0 {
+                    return;
+                }
+                notified.await;
+            }
+        };
+
+        match future::select(Box::pin(drained), Box::pin(runtime::sleep(timeout))).await {
+            Either::Left(_) => true,
+            Either::Right(_) => self.in_flight.load(Ordering::SeqCst) == 0,
+        }
+    }
+
+    fn leave(&self) {
+        if self.in_flight.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.drained.notify_waiters();
+        }
+    }
+}
+
+/// An operation counted as in flight until dropped
+pub(crate) struct InFlight<'a>(&'a Lifecycle);
+
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        self.0.leave();
+    }
+}
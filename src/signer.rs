@@ -0,0 +1,111 @@
+//! Helpers for loading signing keys
+
+use crate::error::{QweryError, Result};
+use solana_sdk::signature::Keypair;
+use solana_sdk::transaction::Transaction;
+
+/// Signs payment transactions, possibly asynchronously
+///
+/// Implement this to route signing to a wallet extension such as Phantom
+/// (`window.solana.signTransaction`) in the browser, or to a remote signer.
+/// [`Keypair`] implements it by signing locally.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use qwery_sdk::error::Result;
+/// use qwery_sdk::signer::AsyncSigner;
+/// use solana_sdk::transaction::Transaction;
+///
+/// struct WalletSigner;
+///
+/// #[async_trait::async_trait]
+/// impl AsyncSigner for WalletSigner {
+///     async fn sign_transaction(&self, tx: Transaction) -> Result<Transaction> {
+///         // Hand the transaction to the wallet and return what it signed
+///         Ok(tx)
+///     }
+/// }
+/// ```
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+pub trait AsyncSigner {
+    /// Add this signer's signature to `tx` and return it
+    async fn sign_transaction(&self, tx: Transaction) -> Result<Transaction>;
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+impl AsyncSigner for Keypair {
+    async fn sign_transaction(&self, mut tx: Transaction) -> Result<Transaction> {
+        let blockhash = tx.message.recent_blockhash;
+        tx.try_partial_sign(&[self], blockhash)
+            .map_err(|e| QweryError::SigningError(e.to_string()))?;
+        Ok(tx)
+    }
+}
+
+/// Load a keypair from a Solana CLI keypair file
+///
+/// The file must contain the JSON byte array written by `solana-keygen`,
+/// e.g. `[12,34,...]` with 64 entries. Not available on `wasm32`.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use qwery_sdk::signer::load_keypair_file;
+/// use solana_sdk::signer::Signer;
+///
+/// let keypair = load_keypair_file("id.json").unwrap();
+/// println!("Loaded {}", keypair.pubkey());
+/// ```
+#[cfg(not(target_arch = "wasm32"))]
+pub fn load_keypair_file(path: impl AsRef<std::path::Path>) -> Result<Keypair> {
+    let path = path.as_ref();
+    let contents = std::fs::read_to_string(path).map_err(|e| {
+        QweryError::SigningError(format!("failed to read {}: {}", path.display(), e))
+    })?;
+
+    let bytes: Vec<u8> = serde_json::from_str(&contents).map_err(|e| {
+        QweryError::SigningError(format!("invalid keypair file {}: {}", path.display(), e))
+    })?;
+
+    Keypair::from_bytes(&bytes).map_err(|e| {
+        QweryError::SigningError(format!("invalid keypair file {}: {}", path.display(), e))
+    })
+}
+
+/// Derive a keypair from a BIP39 mnemonic phrase
+///
+/// Uses the standard Solana derivation path `m/44'/501'/{account}'/0'`, the
+/// same one Phantom and `solana-keygen recover` use, so `account` 0 gives
+/// the wallet's first address. Requires the `mnemonic` feature.
+///
+/// # Example
+///
+/// ```rust
+/// use qwery_sdk::signer::keypair_from_mnemonic;
+/// use solana_sdk::signer::Signer;
+///
+/// let phrase = "abandon abandon abandon abandon abandon abandon \
+///               abandon abandon abandon abandon abandon about";
+/// let keypair = keypair_from_mnemonic(phrase, "", 0).unwrap();
+/// println!("Derived {}", keypair.pubkey());
+///
+/// assert!(keypair_from_mnemonic("not a mnemonic", "", 0).is_err());
+/// ```
+#[cfg(feature = "mnemonic")]
+pub fn keypair_from_mnemonic(phrase: &str, passphrase: &str, account: u32) -> Result<Keypair> {
+    use bip39::{Language, Mnemonic, Seed};
+    use solana_sdk::derivation_path::DerivationPath;
+    use solana_sdk::signer::keypair::keypair_from_seed_and_derivation_path;
+
+    let phrase = phrase.split_whitespace().collect::<Vec<_>>().join(" ");
+    let mnemonic = Mnemonic::from_phrase(&phrase, Language::English)
+        .map_err(|e| QweryError::SigningError(format!("invalid mnemonic: {}", e)))?;
+    let seed = Seed::new(&mnemonic, passphrase);
+
+    let path = DerivationPath::new_bip44(Some(account), Some(0));
+    keypair_from_seed_and_derivation_path(seed.as_bytes(), Some(path))
+        .map_err(|e| QweryError::SigningError(format!("failed to derive keypair: {}", e)))
+}
@@ -0,0 +1,139 @@
+//! Live payment status updates over WebSocket
+
+use crate::client::{QweryClient, API_VERSION, API_VERSION_HEADER};
+use crate::error::{QweryError, Result};
+use crate::types::{PaymentStatus, PaymentStatusUpdate};
+use futures::stream::{self, Stream, StreamExt};
+use futures::SinkExt;
+use std::collections::HashSet;
+use tokio_tungstenite::connect_async;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::http::{HeaderName, HeaderValue};
+use tokio_tungstenite::tungstenite::Message;
+
+impl QweryClient {
+    /// Subscribe to status changes of a single payment
+    ///
+    /// The stream ends once the payment reaches a terminal status.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use qwery_sdk::{QweryClient, Network};
+    /// use futures::StreamExt;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let client = QweryClient::new(Network::Mainnet)?;
+    ///
+    ///     let mut updates = Box::pin(client.subscribe_payment("payment_id").await?);
+    ///     while let Some(status) = updates.next().await {
+    ///         println!("Status: {:?}", status?);
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn subscribe_payment(
+        &self,
+        payment_id: &str,
+    ) -> Result<impl Stream<Item = Result<PaymentStatus>>> {
+        let updates = self.subscribe_payments(&[payment_id.to_string()]).await?;
+        Ok(updates.map(|update| update.map(|u| u.status)))
+    }
+
+    /// Subscribe to status changes of many payments over a single WebSocket
+    ///
+    /// The stream ends once every payment has reached a terminal status. If
+    /// the connection closes first, it yields a [`QweryError::WebSocketError`]
+    /// before ending.
+    pub async fn subscribe_payments(
+        &self,
+        payment_ids: &[String],
+    ) -> Result<impl Stream<Item = Result<PaymentStatusUpdate>>> {
+        let config = self.config();
+        let mut ws_url = self.endpoint_url("/payments/subscribe")?;
+        let scheme = if ws_url.scheme() == "https" { "wss" } else { "ws" };
+        ws_url
+            .set_scheme(scheme)
+            .map_err(|_| QweryError::ConfigError("cannot derive WebSocket URL".to_string()))?;
+
+        let mut request = ws_url
+            .as_str()
+            .into_client_request()
+            .map_err(|e| QweryError::WebSocketError(e.to_string()))?;
+        for (name, value) in &config.default_headers {
+            let name = HeaderName::from_bytes(name.as_str().as_bytes())
+                .map_err(|e| QweryError::ConfigError(e.to_string()))?;
+            let value = HeaderValue::from_bytes(value.as_bytes())
+                .map_err(|e| QweryError::ConfigError(e.to_string()))?;
+            request.headers_mut().insert(name, value);
+        }
+        request
+            .headers_mut()
+            .insert(API_VERSION_HEADER, HeaderValue::from_static(API_VERSION));
+        if let Some(token) = self.access_token().await? {
+            let value = format!("Bearer {}", token.expose_secret())
+                .parse()
+                .map_err(|_| QweryError::ConfigError("invalid API key".to_string()))?;
+            request.headers_mut().insert("Authorization", value);
+        }
+
+        let (mut socket, _) = connect_async(request)
+            .await
+            .map_err(|e| QweryError::WebSocketError(e.to_string()))?;
+
+        let subscribe = serde_json::json!({
+            "type": "subscribe",
+            "payment_ids": payment_ids,
+            "network": config.network.as_str(),
+        });
+        socket
+            .send(Message::Text(subscribe.to_string()))
+            .await
+            .map_err(|e| QweryError::WebSocketError(e.to_string()))?;
+
+        let pending: HashSet<String> = payment_ids.iter().cloned().collect();
+
+        Ok(stream::unfold((socket, pending), |(mut socket, mut pending)| async move {
+            while !pending.is_empty() {
+                // A dropped socket must not look like every payment finishing
+                let closed = || {
+                    QweryError::WebSocketError(format!(
+                        "connection closed with {} payment(s) still pending",
+                        pending.len()
+                    ))
+                };
+                let message = match socket.next().await {
+                    Some(Ok(message)) => message,
+                    Some(Err(e)) => {
+                        let error = QweryError::WebSocketError(e.to_string());
+                        return Some((Err(error), (socket, HashSet::new())));
+                    }
+                    None => return Some((Err(closed()), (socket, HashSet::new()))),
+                };
+
+                let text = match message {
+                    Message::Text(text) => text,
+                    Message::Close(_) => return Some((Err(closed()), (socket, HashSet::new()))),
+                    _ => continue,
+                };
+
+                let update: PaymentStatusUpdate = match serde_json::from_str(&text) {
+                    Ok(update) => update,
+                    Err(e) => return Some((Err(e.into()), (socket, pending))),
+                };
+
+                if !pending.contains(&update.payment_id) {
+                    continue;
+                }
+                if update.status.is_terminal() {
+                    pending.remove(&update.payment_id);
+                }
+                return Some((Ok(update), (socket, pending)));
+            }
+
+            let _ = socket.close(None).await;
+            None
+        }))
+    }
+}
@@ -0,0 +1,249 @@
+//! Higher-level tracking of payments through their whole lifecycle
+
+use crate::client::QweryClient;
+use crate::error::{QweryError, Result};
+use crate::signer::AsyncSigner;
+use crate::types::{PaymentRequest, PaymentResponse, PaymentStatus, PaymentStatusUpdate, SettleResponse};
+use futures::stream::{self, Stream};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::broadcast;
+
+/// Number of transitions buffered for slow `events()` subscribers
+const EVENT_BUFFER: usize = 256;
+
+/// Final state of a payment driven by [`PaymentTracker::track`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrackedPayment {
+    /// The payment that was settled
+    pub payment: PaymentResponse,
+    /// The facilitator's settlement result
+    pub settlement: SettleResponse,
+}
+
+/// Creates, signs, settles and confirms payments, recording every status
+/// transition
+///
+/// Expired payments are recreated and retried up to the client's
+/// [`RetryPolicy::max_retries`](crate::RetryPolicy::max_retries) times.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use qwery_sdk::{QweryClient, Network, PaymentRequest};
+/// use qwery_sdk::tracker::PaymentTracker;
+/// use futures::StreamExt;
+/// use solana_sdk::signature::Keypair;
+///
+/// #[tokio::main]
+/// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     let tracker = PaymentTracker::new(QweryClient::new(Network::Devnet)?, Keypair::new());
+///
+///     let mut events = Box::pin(tracker.events());
+///     tokio::spawn(async move {
+///         while let Some(update) = events.next().await {
+///             println!("{} -> {:?}", update.payment_id, update.status);
+///         }
+///     });
+///
+///     let tracked = tracker.track(PaymentRequest {
+///         amount: 0.01,
+///         token: "SOL".to_string(),
+///         recipient: "recipient_address".to_string(),
+///         ..Default::default()
+///     }).await?;
+///
+///     println!("Status: {:?}", tracker.status(&tracked.payment.payment_id));
+///     Ok(())
+/// }
+/// ```
+pub struct PaymentTracker<S> {
+    client: QweryClient,
+    signer: S,
+    confirmation_timeout: Duration,
+    history: Arc<Mutex<HashMap<String, Vec<PaymentStatus>>>>,
+    events: broadcast::Sender<PaymentStatusUpdate>,
+}
+
+impl<S: AsyncSigner> PaymentTracker<S> {
+    /// Create a tracker that signs every payment with `signer`
+    pub fn new(client: QweryClient, signer: S) -> Self {
+        let (events, _) = broadcast::channel(EVENT_BUFFER);
+        Self {
+            client,
+            signer,
+            confirmation_timeout: Duration::from_secs(60),
+            history: Arc::new(Mutex::new(HashMap::new())),
+            events,
+        }
+    }
+
+    /// Set how long to wait for a settled payment to be verified (default 60s)
+    pub fn with_confirmation_timeout(mut self, timeout: Duration) -> Self {
+        self.confirmation_timeout = timeout;
+        self
+    }
+
+    /// Create a payment and drive it through signing, settlement and
+    /// confirmation
+    ///
+    /// If the payment expires before it settles, a new payment is created
+    /// from the same request and tried again. A payment already expired by
+    /// the client's [`clock`](crate::QweryConfig::clock) is recreated without
+    /// being signed.
+    pub async fn track(&self, request: PaymentRequest) -> Result<TrackedPayment> {
+        let mut attempt = 0;
+
+        loop {
+            let payment = self.client.create_payment(request.clone()).await?;
+            self.record(&payment.payment_id, PaymentStatus::Pending);
+
+            let result = if payment.is_expired(&*self.client.config().clock) {
+                // The facilitator would refuse it, so don't ask the signer
+                Err(QweryError::SettlementFailed {
+                    status: "expired".to_string(),
+                    error: Some("payment expired before it was signed".to_string()),
+                    logs: Vec::new(),
+                })
+            } else {
+                self.client.sign_and_settle_async(&payment, &self.signer).await
+            };
+
+            match result {
+                Ok(settlement) => {
+                    self.record(&payment.payment_id, PaymentStatus::Submitted);
+                    if let Some(ref signature) = settlement.signature {
+                        self.client
+                            .wait_for_confirmation(signature, self.confirmation_timeout)
+                            .await?;
+                    }
+                    self.record(&payment.payment_id, PaymentStatus::Settled);
+                    return Ok(TrackedPayment { payment, settlement });
+                }
+                Err(QweryError::SettlementFailed { ref status, .. })
+                    if PaymentStatus::parse(status) == PaymentStatus::Expired
+                        && attempt < self.client.config().retry_policy.max_retries =>
+                {
+                    self.record(&payment.payment_id, PaymentStatus::Expired);
+                    attempt += 1;
+                }
+                Err(e) => {
+                    let status = match e {
+                        QweryError::SettlementFailed { ref status, .. } => PaymentStatus::parse(status),
+                        _ => PaymentStatus::Failed,
+                    };
+                    self.record(&payment.payment_id, status);
+                    return Err(e);
+                }
+            }
+        }
+    }
+
+    /// Latest known status of a tracked payment
+    pub fn status(&self, payment_id: &str) -> Option<PaymentStatus> {
+        self.history.lock().unwrap().get(payment_id)?.last().copied()
+    }
+
+    /// Every status a tracked payment has gone through, oldest first
+    pub fn history(&self, payment_id: &str) -> Vec<PaymentStatus> {
+        self.history
+            .lock()
+            .unwrap()
+            .get(payment_id)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Stream of status transitions for payments tracked from now on
+    ///
+    /// A subscriber that falls more than 256 transitions behind skips the
+    /// oldest ones.
+    pub fn events(&self) -> impl Stream<Item = PaymentStatusUpdate> {
+        stream::unfold(self.events.subscribe(), |mut receiver| async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(update) => return Some((update, receiver)),
+                    Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => return None,
+                }
+            }
+        })
+    }
+
+    /// Record a transition and notify subscribers
+    fn record(&self, payment_id: &str, status: PaymentStatus) {
+        self.history
+            .lock()
+            .unwrap()
+            .entry(payment_id.to_string())
+            .or_default()
+            .push(status);
+
+        // No subscribers is fine
+        let _ = self.events.send(PaymentStatusUpdate {
+            payment_id: payment_id.to_string(),
+            status,
+        });
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+    use crate::clock::MockClock;
+    use crate::mock_facilitator::MockFacilitator;
+    use crate::types::{QweryConfig, RetryPolicy};
+    use solana_sdk::signature::Keypair;
+
+    #[tokio::test]
+    async fn track_recreates_expired_payments_without_signing_them() {
+        let mock = MockFacilitator::start(|_| {
+            let payment = r#"{
+                "payment_id": "pay_123",
+                "transaction": "",
+                "amount": 0.01,
+                "token": "SOL",
+                "recipient": "9xQeWvG816bUx9EPjHmaT23yvVM2ZWbrrpZb9PusVFin",
+                "network": "solana",
+                "status": "pending",
+                "expires_at": "2025-01-01T00:00:00Z"
+            }"#;
+            (200, payment.to_string())
+        })
+        .await;
+        let client = QweryClient::with_config(QweryConfig {
+            facilitator_url: mock.url.clone(),
+            clock: Arc::new(MockClock::new(Duration::from_secs(1_735_689_600))),
+            retry_policy: RetryPolicy {
+                max_retries: 1,
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .unwrap();
+        let tracker = PaymentTracker::new(client, Keypair::new());
+
+        let result = tracker
+            .track(PaymentRequest {
+                amount: 0.01,
+                token: "SOL".to_string(),
+                recipient: "9xQeWvG816bUx9EPjHmaT23yvVM2ZWbrrpZb9PusVFin".to_string(),
+                ..Default::default()
+            })
+            .await;
+
+        assert!(matches!(result, Err(QweryError::SettlementFailed { ref status, .. }) if status == "expired"));
+        let paths: Vec<String> = mock.received().into_iter().map(|request| request.path).collect();
+        assert_eq!(paths, ["/payments/create", "/payments/create"]);
+        assert_eq!(
+            tracker.history("pay_123"),
+            [
+                PaymentStatus::Pending,
+                PaymentStatus::Expired,
+                PaymentStatus::Pending,
+                PaymentStatus::Expired,
+            ]
+        );
+    }
+}
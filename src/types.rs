@@ -1,10 +1,54 @@
 //! Types used in the Qwery SDK
+//!
+//! Response types ignore fields they don't know and default fields the
+//! facilitator may leave out, so newer facilitator versions don't break
+//! older SDK builds.
 
+use crate::auth::TokenProvider;
+use crate::clock::{self, Clock, SystemClock};
+use crate::error::{QweryError, Result};
+use crate::metrics::MetricsObserver;
+use base64::engine::general_purpose::{STANDARD_NO_PAD, URL_SAFE, URL_SAFE_NO_PAD};
+use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+use serde::de::value::StrDeserializer;
+use serde::de::IntoDeserializer;
+use reqwest::header::HeaderMap;
 use serde::{Deserialize, Serialize};
+#[cfg(feature = "signing")]
+use solana_sdk::hash::Hash;
+#[cfg(feature = "signing")]
+use solana_sdk::pubkey::Pubkey;
+#[cfg(feature = "signing")]
+use solana_sdk::signature::Signature;
+#[cfg(feature = "signing")]
+use solana_sdk::transaction::Transaction;
 use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Maximum memo length in bytes accepted by the SPL Memo program
+pub const MAX_MEMO_LENGTH: usize = 566;
+
+/// Largest serialized transaction, in bytes, that fits in a Solana packet
+pub const MAX_TRANSACTION_SIZE: usize = 1232;
+
+/// Lamports a new system account must hold to be rent exempt
+///
+/// A SOL payment to a wallet that doesn't exist yet fails below this.
+pub const RENT_EXEMPT_MINIMUM_LAMPORTS: u64 = 890_880;
+
+/// Metadata key prefix reserved for the facilitator's own use
+///
+/// Keys starting with it, in any case, are rejected by
+/// [`PaymentRequest::validate`].
+pub const RESERVED_METADATA_PREFIX: &str = "qwery_";
 
 /// Network to connect to
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum Network {
     /// Solana Mainnet
     Mainnet,
@@ -20,31 +64,768 @@ impl Network {
             Network::Devnet => "solana-devnet",
         }
     }
+
+    /// Public Solana RPC endpoint for this network
+    ///
+    /// Public endpoints are heavily rate limited, so prefer a dedicated
+    /// provider in production.
+    ///
+    /// ```rust
+    /// use qwery_sdk::{Network, QweryConfig};
+    ///
+    /// let config = QweryConfig {
+    ///     network: Network::Devnet,
+    ///     rpc_url: Some(Network::Devnet.default_rpc_url().to_string()),
+    ///     ..Default::default()
+    /// };
+    /// assert_eq!(config.rpc_url.as_deref(), Some("https://api.devnet.solana.com"));
+    /// ```
+    pub fn default_rpc_url(&self) -> &'static str {
+        match self {
+            Network::Mainnet => "https://api.mainnet-beta.solana.com",
+            Network::Devnet => "https://api.devnet.solana.com",
+        }
+    }
+
+    /// Parse a wire network name as returned by [`Network::as_str`]
+    pub(crate) fn from_wire(network: &str) -> Option<Network> {
+        match network {
+            "solana" => Some(Network::Mainnet),
+            "solana-devnet" => Some(Network::Devnet),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Network {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Network::Mainnet => "Mainnet",
+            Network::Devnet => "Devnet",
+        })
+    }
+}
+
+/// Parse a network name, case-insensitively
+///
+/// ```rust
+/// use qwery_sdk::Network;
+///
+/// let network: Network = "devnet".parse().unwrap();
+/// assert_eq!(network, Network::Devnet);
+/// ```
+impl FromStr for Network {
+    type Err = QweryError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "mainnet" => Ok(Network::Mainnet),
+            "devnet" => Ok(Network::Devnet),
+            _ => Err(QweryError::ConfigError(format!("unknown network: {}", s))),
+        }
+    }
+}
+
+/// Token supported by the facilitator
+///
+/// Serializes, displays and parses as the uppercase symbol, e.g. `USDC`.
+/// These strings are stable across SDK versions, so they can be stored, for
+/// example as a database enum, and read back.
+///
+/// ```rust
+/// use qwery_sdk::Token;
+///
+/// let stored = Token::Usdc.to_string();
+/// assert_eq!(stored, "USDC");
+/// assert_eq!(stored.parse::<Token>().unwrap(), Token::Usdc);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum Token {
+    /// Native SOL
+    Sol,
+    /// USD Coin
+    Usdc,
+    /// Tether USD
+    Usdt,
+}
+
+impl Token {
+    /// Get the token symbol as sent to the facilitator
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Token::Sol => "SOL",
+            Token::Usdc => "USDC",
+            Token::Usdt => "USDT",
+        }
+    }
+
+    /// Number of decimal places in one whole token
+    pub fn decimals(&self) -> u8 {
+        match self {
+            Token::Sol => 9,
+            Token::Usdc | Token::Usdt => 6,
+        }
+    }
+
+    /// Smallest payment, in base units, worth sending on `network`
+    ///
+    /// On mainnet smaller payments cost more in fees than they move: a SOL
+    /// payment must cover a 5000 lamport signature fee, stablecoins must be
+    /// at least 0.001. On devnet any non-zero amount is allowed. This doesn't
+    /// include [`RENT_EXEMPT_MINIMUM_LAMPORTS`], which only applies to SOL
+    /// sent to a new account.
+    pub fn minimum_amount(&self, network: Network) -> u64 {
+        match (network, self) {
+            (Network::Devnet, _) => 1,
+            (Network::Mainnet, Token::Sol) => 5_000,
+            (Network::Mainnet, Token::Usdc | Token::Usdt) => 1_000,
+        }
+    }
+
+    /// Convert a decimal amount into base units (lamports for SOL)
+    pub fn to_base_units(&self, amount: f64) -> u64 {
+        (amount * 10f64.powi(self.decimals() as i32)).round() as u64
+    }
+
+    /// Render base units as a human-readable amount with the token symbol
+    ///
+    /// Trailing zeros are trimmed, but stablecoins keep two decimal places.
+    ///
+    /// ```rust
+    /// use qwery_sdk::Token;
+    ///
+    /// assert_eq!(Token::Usdc.format_amount(1_500_000), "1.50 USDC");
+    /// ```
+    pub fn format_amount(&self, base_units: u64) -> String {
+        let scale = 10u64.pow(self.decimals() as u32);
+        let fraction = format!(
+            "{:0width$}",
+            base_units % scale,
+            width = self.decimals() as usize
+        );
+
+        let min_decimals = match self {
+            Token::Sol => 0,
+            Token::Usdc | Token::Usdt => 2,
+        };
+        let trimmed = fraction.trim_end_matches('0');
+        let fraction = &fraction[..trimmed.len().max(min_decimals)];
+
+        if fraction.is_empty() {
+            format!("{} {}", base_units / scale, self.as_str())
+        } else {
+            format!("{}.{} {}", base_units / scale, fraction, self.as_str())
+        }
+    }
+
+    /// Parse a decimal amount, optionally followed by the token symbol, into
+    /// base units without floating point rounding
+    ///
+    /// Returns [`QweryError::ConfigError`] for malformed input, amounts with
+    /// more decimal places than the token has, or a different symbol.
+    ///
+    /// ```rust
+    /// use qwery_sdk::Token;
+    ///
+    /// assert_eq!(Token::Usdc.parse_amount("1.50 USDC").unwrap(), 1_500_000);
+    /// ```
+    pub fn parse_amount(&self, s: &str) -> Result<u64> {
+        let invalid = || QweryError::ConfigError(format!("invalid {} amount: {:?}", self, s));
+
+        let mut parts = s.split_whitespace();
+        let number = parts.next().ok_or_else(invalid)?;
+        if let Some(symbol) = parts.next() {
+            if !symbol.eq_ignore_ascii_case(self.as_str()) || parts.next().is_some() {
+                return Err(invalid());
+            }
+        }
+
+        let (whole, fraction) = number.split_once('.').unwrap_or((number, ""));
+        let decimals = self.decimals() as usize;
+        if (whole.is_empty() && fraction.is_empty())
+            || fraction.len() > decimals
+            || !whole.bytes().chain(fraction.bytes()).all(|b| b.is_ascii_digit())
+        {
+            return Err(invalid());
+        }
+
+        let whole: u64 = if whole.is_empty() { 0 } else { whole.parse().map_err(|_| invalid())? };
+        let fraction: u64 = format!("{:0<width$}", fraction, width = decimals)
+            .parse()
+            .map_err(|_| invalid())?;
+
+        whole
+            .checked_mul(10u64.pow(decimals as u32))
+            .and_then(|units| units.checked_add(fraction))
+            .ok_or_else(invalid)
+    }
+
+    /// Canonical SPL mint address for the token on `network`, `None` for
+    /// native SOL or tokens without a mint on that network
+    pub(crate) fn mint_str(&self, network: Network) -> Option<&'static str> {
+        match (self, network) {
+            (Token::Sol, _) => None,
+            (Token::Usdc, Network::Mainnet) => Some("EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v"),
+            (Token::Usdc, Network::Devnet) => Some("4zMMC9srt5Ri5X14GAgXhaHii3GnPAEERYPJgZJDncDU"),
+            (Token::Usdt, Network::Mainnet) => Some("Es9vMFrzaCERmJfrF4H2FYD4KCoNkY11McCe8BenwNYB"),
+            (Token::Usdt, Network::Devnet) => None,
+        }
+    }
+
+    /// Canonical SPL mint for the token on `network`, `None` for native SOL
+    /// or tokens without a mint on that network
+    ///
+    /// ```rust
+    /// use qwery_sdk::{Network, Token};
+    ///
+    /// let usdc = Token::Usdc.mint_address(Network::Mainnet).unwrap();
+    /// assert_eq!(usdc.to_string(), "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v");
+    /// ```
+    #[cfg(feature = "signing")]
+    pub fn mint_address(&self, network: Network) -> Option<Pubkey> {
+        self.mint_str(network)
+            .map(|mint| Pubkey::from_str(mint).expect("valid mint address"))
+    }
+
+    /// Convert base units back into a decimal amount
+    pub fn from_base_units(&self, base_units: u64) -> f64 {
+        base_units as f64 / 10f64.powi(self.decimals() as i32)
+    }
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Token {
+    type Err = QweryError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_uppercase().as_str() {
+            "SOL" => Ok(Token::Sol),
+            "USDC" => Ok(Token::Usdc),
+            "USDT" => Ok(Token::Usdt),
+            _ => Err(QweryError::ConfigError(format!("unknown token: {}", s))),
+        }
+    }
+}
+
+/// A secret value, such as an API key, that is redacted from `Debug` output
+///
+/// ```rust
+/// use qwery_sdk::SecretString;
+///
+/// let key = SecretString::new("sk_live_123");
+/// assert_eq!(format!("{:?}", key), "\"***\"");
+/// assert_eq!(key.expose_secret(), "sk_live_123");
+/// ```
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct SecretString(String);
+
+impl SecretString {
+    /// Create a new secret
+    pub fn new(secret: impl Into<String>) -> Self {
+        Self(secret.into())
+    }
+
+    /// Get the underlying secret value
+    pub fn expose_secret(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Debug for SecretString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("\"***\"")
+    }
+}
+
+impl From<String> for SecretString {
+    fn from(secret: String) -> Self {
+        Self(secret)
+    }
+}
+
+impl From<&str> for SecretString {
+    fn from(secret: &str) -> Self {
+        Self(secret.to_string())
+    }
 }
 
 /// Configuration for the Qwery client
-#[derive(Debug, Clone)]
+///
+/// Missing fields fall back to their [`Default`] values when deserializing.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct QweryConfig {
     /// Facilitator API URL
+    ///
+    /// May include a path, e.g. `https://api.example.com/qwery`, when the
+    /// facilitator is served below the root.
     pub facilitator_url: String,
+    /// Optional path prefix for every endpoint, e.g. `v1`
+    ///
+    /// Endpoints are resolved as `facilitator_url/api_base_path/endpoint`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub api_base_path: Option<String>,
     /// Network to use
     pub network: Network,
     /// Optional API key
-    pub api_key: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub api_key: Option<SecretString>,
+    /// Source of short-lived bearer tokens, used instead of `api_key` when set
+    #[serde(skip)]
+    pub token_provider: Option<Arc<dyn TokenProvider>>,
+    /// Time source for `wait_for_confirmation` timeouts and `health_cached`
+    ///
+    /// Defaults to the system clock; swap in a `MockClock` to test timeout
+    /// paths without waiting.
+    #[serde(skip, default = "default_clock")]
+    pub clock: Arc<dyn Clock>,
+    /// Check the payer's balance before creating a payment
+    ///
+    /// Only applies to requests with a `payer` set. Adds a round trip to the
+    /// facilitator per payment.
+    pub balance_preflight: bool,
+    /// Timeout for each HTTP request, in seconds when serialized
+    #[serde(with = "duration_secs")]
+    pub timeout: Duration,
+    /// Timeout for establishing a connection, in seconds when serialized
+    ///
+    /// Exceeding it fails with `QweryError::ConnectTimeout` rather than
+    /// `QweryError::ReadTimeout`.
+    #[serde(with = "duration_secs")]
+    pub connect_timeout: Duration,
+    /// Maximum idle connections kept open per host
+    pub pool_max_idle_per_host: usize,
+    /// How long an idle connection is kept open, in seconds when serialized
+    #[serde(with = "duration_secs")]
+    pub pool_idle_timeout: Duration,
+    /// Solana RPC endpoint for features that read chain state directly
+    ///
+    /// Blockhash refresh and other chain reads fail with
+    /// `QweryError::ConfigError` when it isn't set. `Network::default_rpc_url`
+    /// gives the public endpoint for each network.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rpc_url: Option<String>,
+    /// Retry behaviour for rate-limited and unavailable responses
+    pub retry_policy: RetryPolicy,
+    /// What `create_payment` does with amounts finer than the token's decimals
+    pub rounding_policy: RoundingPolicy,
+    /// Polling behaviour for `wait_for_confirmation`
+    pub poll: PollConfig,
+    /// Optional observer notified of payment lifecycle events
+    #[serde(skip)]
+    pub metrics: Option<Arc<dyn MetricsObserver>>,
+    /// Sign payments but never submit them for settlement
+    ///
+    /// Settle calls decode the signed transaction locally and return a
+    /// synthetic successful response instead of calling the facilitator,
+    /// so integrations can be exercised without moving funds.
+    pub dry_run: bool,
+    /// Log a `tracing` warning listing payments created but never settled
+    /// when the last clone of the client is dropped
+    ///
+    /// Meant for development, to catch integrations that leave payments
+    /// pending. Only payments created and settled through the same client
+    /// are tracked.
+    pub warn_unsettled_on_drop: bool,
+    /// Most bytes of an error response body kept in `QweryError::ApiError`
+    ///
+    /// Longer bodies are cut off and marked as truncated, bounding memory
+    /// use on the error path.
+    pub max_error_body_size: usize,
+    /// How many finalized verification results `verify_payment` keeps in memory
+    ///
+    /// A finalized transaction's verification never changes, so repeated
+    /// checks of the same signature are answered from the cache, evicting
+    /// the least recently used entry when full. Results that aren't
+    /// finalized are never cached. `0` disables the cache.
+    pub verify_cache_size: usize,
+    /// Most HTTP requests this client, and its clones, have in flight at once
+    ///
+    /// Further requests wait for a slot instead of failing. Retries wait
+    /// again for each attempt. `None` means no limit.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_concurrent_requests: Option<usize>,
+    /// Most payments `list_all_payments` collects before giving up
+    ///
+    /// Reaching it fails with `QweryError::TooManyResults` instead of
+    /// returning a truncated list.
+    pub max_list_results: usize,
+    /// Headers added to every request, e.g. a tenant ID for a gateway
+    ///
+    /// `Authorization`, `Content-Type` and `X-Qwery-Api-Version` are set by
+    /// the client and rejected here with `QweryError::ConfigError`.
+    /// Serialized as a map of header names to values.
+    #[serde(with = "header_map", skip_serializing_if = "HeaderMap::is_empty")]
+    pub default_headers: HeaderMap,
+    /// Largest amount, in base units, a single payment may move per token
+    ///
+    /// `create_payment` and `sign_and_settle` reject larger payments with
+    /// `QweryError::ConfigError` before contacting the facilitator. Tokens
+    /// without an entry are not capped.
+    ///
+    /// ```rust
+    /// use qwery_sdk::{PaymentRequest, QweryClient, QweryConfig, QweryError, Token};
+    /// use std::collections::HashMap;
+    ///
+    /// let client = QweryClient::with_config(QweryConfig {
+    ///     max_payment_amount: Some(HashMap::from([(Token::Usdc, 100_000_000)])),
+    ///     ..Default::default()
+    /// }).unwrap();
+    ///
+    /// let request = PaymentRequest {
+    ///     amount: 250.0,
+    ///     token: "USDC".to_string(),
+    ///     recipient: "recipient_address".to_string(),
+    ///     ..Default::default()
+    /// };
+    /// let result = tokio_test::block_on(client.create_payment(request));
+    /// assert!(matches!(result, Err(QweryError::ConfigError(_))));
+    /// ```
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_payment_amount: Option<HashMap<Token, u64>>,
+    /// Text appended to the `qwery-sdk-rust/<version>` user agent
+    ///
+    /// Ignored on `wasm32`, where the browser sets the user agent.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_agent_suffix: Option<String>,
+    /// Replace an expired blockhash before signing in `sign_and_settle`, and
+    /// sign again when it expires before the transaction lands
+    ///
+    /// The check before signing requires `rpc_url` and is skipped on
+    /// `wasm32`. A transaction the facilitator already signed can't be given
+    /// a new blockhash locally, so signing it fails with
+    /// `QweryError::SigningError` instead. See `max_blockhash_retries` for
+    /// expiry after signing.
+    pub refresh_blockhash: bool,
+    /// Check the facilitator's health before creating a payment
+    ///
+    /// If the configured network isn't operational, `create_payment` fails
+    /// fast with `QweryError::FacilitatorUnhealthy`. The health response is
+    /// cached briefly, but this still adds a request now and then, so it's
+    /// off by default.
+    pub preflight_health_check: bool,
+    /// How many times `sign_and_settle` re-signs and resubmits a payment
+    /// whose blockhash expired before it landed
+    ///
+    /// Each attempt fetches the payment again for a transaction the
+    /// facilitator rebuilt on a fresh blockhash and signs that; the stale
+    /// transaction is never resubmitted. Only used with `refresh_blockhash`.
+    pub max_blockhash_retries: u32,
+}
+
+fn default_clock() -> Arc<dyn Clock> {
+    Arc::new(SystemClock)
 }
 
 impl Default for QweryConfig {
     fn default() -> Self {
         Self {
             facilitator_url: "https://facilitator.qwery.xyz".to_string(),
+            api_base_path: None,
             network: Network::Mainnet,
             api_key: None,
+            token_provider: None,
+            clock: default_clock(),
+            balance_preflight: false,
+            timeout: Duration::from_secs(30),
+            connect_timeout: Duration::from_secs(10),
+            pool_max_idle_per_host: 10,
+            pool_idle_timeout: Duration::from_secs(90),
+            rpc_url: None,
+            retry_policy: RetryPolicy::default(),
+            rounding_policy: RoundingPolicy::default(),
+            poll: PollConfig::default(),
+            metrics: None,
+            refresh_blockhash: false,
+            preflight_health_check: false,
+            max_blockhash_retries: 2,
+            user_agent_suffix: None,
+            dry_run: false,
+            warn_unsettled_on_drop: false,
+            max_payment_amount: None,
+            default_headers: HeaderMap::new(),
+            max_error_body_size: 64 * 1024,
+            verify_cache_size: 0,
+            max_list_results: 10_000,
+            max_concurrent_requests: None,
+        }
+    }
+}
+
+impl QweryConfig {
+    /// Load a configuration from a `.toml` or `.json` file
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use qwery_sdk::{QweryClient, QweryConfig};
+    ///
+    /// let config = QweryConfig::from_file("qwery.toml").unwrap();
+    /// let client = QweryClient::with_config(config).unwrap();
+    /// ```
+    pub fn from_file(path: impl AsRef<Path>) -> Result<QweryConfig> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path).map_err(|e| {
+            QweryError::ConfigError(format!("failed to read {}: {}", path.display(), e))
+        })?;
+
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => toml::from_str(&contents).map_err(|e| {
+                QweryError::ConfigError(format!("failed to parse {}: {}", path.display(), e))
+            }),
+            Some("json") => serde_json::from_str(&contents).map_err(|e| {
+                QweryError::ConfigError(format!("failed to parse {}: {}", path.display(), e))
+            }),
+            _ => Err(QweryError::ConfigError(format!(
+                "unsupported config file extension: {}",
+                path.display()
+            ))),
+        }
+    }
+}
+
+/// Retry policy for requests rejected with HTTP 429 or 503
+///
+/// A `Retry-After` header on the response takes precedence over the
+/// exponential backoff. Set `max_retries` to 0 to disable retries and handle
+/// [`QweryError::RateLimited`] yourself.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetryPolicy {
+    /// Maximum number of retries after the first attempt
+    pub max_retries: u32,
+    /// Backoff before the first retry, in milliseconds when serialized
+    #[serde(with = "duration_millis")]
+    pub initial_backoff: Duration,
+    /// Upper bound on the backoff between retries, in milliseconds when serialized
+    #[serde(with = "duration_millis")]
+    pub max_backoff: Duration,
+    /// Time limit for a request including all of its retries, in
+    /// milliseconds when serialized
+    ///
+    /// No retry is started that would wait past it, and each attempt's
+    /// timeout is shortened to end by it (except on `wasm32`), so the
+    /// request fails with its last error instead. `None` means retries are only limited by
+    /// `max_retries`.
+    #[serde(with = "option_duration_millis", skip_serializing_if = "Option::is_none")]
+    pub total_deadline: Option<Duration>,
+}
+
+impl RetryPolicy {
+    /// Exponential backoff before retry number `attempt` (starting at 0)
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        self.initial_backoff
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_backoff)
+    }
+
+    /// Whether to make retry number `attempt` (starting at 0) after waiting
+    /// `delay`, when `elapsed` has passed since the first attempt
+    pub fn should_retry(&self, attempt: u32, elapsed: Duration, delay: Duration) -> bool {
+        attempt < self.max_retries
+            && self
+                .total_deadline
+                .is_none_or(|deadline| elapsed.saturating_add(delay) <= deadline)
+    }
+
+    /// Time left before `total_deadline`, if one is set
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn remaining(&self, elapsed: Duration) -> Option<Duration> {
+        self.total_deadline.map(|deadline| deadline.saturating_sub(elapsed))
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(10),
+            total_deadline: None,
+        }
+    }
+}
+
+/// How amounts with more decimal places than the token supports are handled
+///
+/// ```rust
+/// use qwery_sdk::{RoundingPolicy, Token};
+///
+/// assert_eq!(RoundingPolicy::Round.apply(Token::Usdc, 0.1234567).unwrap(), 0.123457);
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RoundingPolicy {
+    /// Fail with `QweryError::ConfigError`
+    #[default]
+    Reject,
+    /// Round to the nearest base unit
+    Round,
+}
+
+impl RoundingPolicy {
+    /// Check `amount` against the token's decimals, rounding it if allowed
+    pub fn apply(&self, token: Token, amount: f64) -> Result<f64> {
+        // Only plain non-negative amounts have a decimal expansion to check
+        if !amount.is_finite() || amount < 0.0 {
+            return Ok(amount);
+        }
+
+        // Display prints the shortest decimal that round-trips, never an exponent
+        if token.parse_amount(&amount.to_string()).is_ok() {
+            return Ok(amount);
+        }
+
+        match self {
+            RoundingPolicy::Reject => Err(QweryError::ConfigError(format!(
+                "amount {} has more than {} decimal places for {}",
+                amount,
+                token.decimals(),
+                token
+            ))),
+            RoundingPolicy::Round => Ok(token.from_base_units(token.to_base_units(amount))),
+        }
+    }
+}
+
+/// Polling interval for confirmation waits, backing off from
+/// `initial_interval` by `backoff_factor` up to `max_interval`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PollConfig {
+    /// Interval before the second poll, in milliseconds when serialized
+    #[serde(with = "duration_millis")]
+    pub initial_interval: Duration,
+    /// Upper bound on the interval, in milliseconds when serialized
+    #[serde(with = "duration_millis")]
+    pub max_interval: Duration,
+    /// Factor the interval grows by after each poll
+    pub backoff_factor: f64,
+}
+
+impl PollConfig {
+    /// Interval to wait after poll number `attempt` (starting at 0)
+    pub fn interval(&self, attempt: u32) -> Duration {
+        self.initial_interval
+            .mul_f64(self.backoff_factor.max(1.0).powi(attempt as i32))
+            .min(self.max_interval)
+    }
+}
+
+impl Default for PollConfig {
+    fn default() -> Self {
+        Self {
+            initial_interval: Duration::from_millis(500),
+            max_interval: Duration::from_secs(5),
+            backoff_factor: 1.5,
+        }
+    }
+}
+
+/// Serialize a [`Duration`] as whole seconds
+mod duration_secs {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    pub fn serialize<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(duration.as_secs())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        u64::deserialize(deserializer).map(Duration::from_secs)
+    }
+}
+
+/// Serialize an optional value, such as a [`Pubkey`] or [`Hash`], as its
+/// base58 string
+#[cfg(feature = "signing")]
+mod display_string {
+    use serde::Serializer;
+    use std::fmt::Display;
+
+    pub fn serialize<T: Display, S: Serializer>(
+        value: &Option<T>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match value {
+            Some(value) => serializer.collect_str(value),
+            None => serializer.serialize_none(),
+        }
+    }
+}
+
+/// Serialize a [`HeaderMap`] as a map of header names to string values
+mod header_map {
+    use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::collections::HashMap;
+
+    pub fn serialize<S: Serializer>(headers: &HeaderMap, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(
+            headers
+                .iter()
+                .filter_map(|(name, value)| Some((name.as_str(), value.to_str().ok()?))),
+        )
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<HeaderMap, D::Error> {
+        HashMap::<String, String>::deserialize(deserializer)?
+            .into_iter()
+            .map(|(name, value)| {
+                let name = HeaderName::try_from(name.as_str()).map_err(D::Error::custom)?;
+                let value = HeaderValue::try_from(value.as_str()).map_err(D::Error::custom)?;
+                Ok((name, value))
+            })
+            .collect()
+    }
+}
+
+/// Serialize a [`Duration`] as whole milliseconds
+mod duration_millis {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    pub fn serialize<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(duration.as_millis() as u64)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        u64::deserialize(deserializer).map(Duration::from_millis)
+    }
+}
+
+/// Serialize an optional [`Duration`] as whole milliseconds
+mod option_duration_millis {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    pub fn serialize<S: Serializer>(
+        duration: &Option<Duration>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match duration {
+            Some(duration) => serializer.serialize_some(&(duration.as_millis() as u64)),
+            None => serializer.serialize_none(),
         }
     }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Duration>, D::Error> {
+        Ok(Option::<u64>::deserialize(deserializer)?.map(Duration::from_millis))
+    }
 }
 
 /// Request to create a payment
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Default, Serialize)]
 pub struct PaymentRequest {
     /// Amount to pay
     pub amount: f64,
@@ -53,16 +834,283 @@ pub struct PaymentRequest {
     /// Recipient wallet address
     pub recipient: String,
     /// Optional metadata
+    ///
+    /// Keys starting with [`RESERVED_METADATA_PREFIX`] are reserved.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub metadata: Option<HashMap<String, String>>,
+    /// Optional structured metadata, sent as JSON alongside `metadata`
+    ///
+    /// Top-level keys starting with [`RESERVED_METADATA_PREFIX`] are reserved.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata_json: Option<serde_json::Value>,
+    /// Optional wallet address that will sign the payment
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub payer: Option<String>,
+    /// Optional on-chain memo, added to the transaction as a Memo program instruction
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memo: Option<String>,
+    /// Optional priority fee in micro-lamports per compute unit
+    ///
+    /// The facilitator still pays all network fees, including this priority
+    /// fee; it only tells the facilitator how aggressively to bid for block
+    /// space during congestion.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub priority_fee_micro_lamports: Option<u64>,
+    /// Optional compute unit limit for the transaction
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub compute_unit_limit: Option<u32>,
+    /// Whether the facilitator adds an idempotent create-ATA instruction for
+    /// the recipient of an SPL token payment (default `true`)
+    ///
+    /// Set to `false` when the recipient's token account is known to exist,
+    /// to save rent and transaction size. If it turns out to be missing,
+    /// settlement fails with `QweryError::RecipientAccountMissing`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub create_recipient_ata: Option<bool>,
+    /// Optional account to pay the transaction fees instead of the facilitator
+    ///
+    /// By default the facilitator pays all network fees. When set, the
+    /// transaction is built with this account as fee payer, so it must also
+    /// sign; use [`QweryClient::sign_and_settle_with`] when it isn't the payer.
+    ///
+    /// [`QweryClient::sign_and_settle_with`]: crate::QweryClient::sign_and_settle_with
+    #[cfg(feature = "signing")]
+    #[serde(skip_serializing_if = "Option::is_none", serialize_with = "display_string::serialize")]
+    pub fee_payer: Option<Pubkey>,
+    /// Optional blockhash to build the transaction against instead of the
+    /// facilitator's choice
+    ///
+    /// See [`QweryClient::get_latest_blockhash`].
+    ///
+    /// [`QweryClient::get_latest_blockhash`]: crate::QweryClient::get_latest_blockhash
+    #[cfg(feature = "signing")]
+    #[serde(skip_serializing_if = "Option::is_none", serialize_with = "display_string::serialize")]
+    pub recent_blockhash: Option<Hash>,
+    /// Optional key added as a read-only account to the transfer instruction
+    ///
+    /// Following the Solana Pay reference convention, a unique key per order
+    /// lets the transaction be found on chain without its signature; see
+    /// [`QweryClient::find_by_reference`].
+    ///
+    /// [`QweryClient::find_by_reference`]: crate::QweryClient::find_by_reference
+    #[cfg(feature = "signing")]
+    #[serde(skip_serializing_if = "Option::is_none", serialize_with = "display_string::serialize")]
+    pub reference: Option<Pubkey>,
+}
+
+impl PaymentRequest {
+    /// Start building a payment request
+    pub fn builder() -> PaymentRequestBuilder {
+        PaymentRequestBuilder::default()
+    }
+
+    /// Validate the request locally before it is sent to the facilitator
+    ///
+    /// ```rust
+    /// use qwery_sdk::PaymentRequest;
+    /// use std::collections::HashMap;
+    ///
+    /// let request = PaymentRequest {
+    ///     metadata: Some(HashMap::from([("qwery_source".to_string(), "web".to_string())])),
+    ///     ..Default::default()
+    /// };
+    /// assert!(request.validate().is_err());
+    /// ```
+    pub fn validate(&self) -> Result<()> {
+        if let Some(ref memo) = self.memo {
+            if memo.len() > MAX_MEMO_LENGTH {
+                return Err(QweryError::ConfigError(format!(
+                    "memo is {} bytes, maximum is {}",
+                    memo.len(),
+                    MAX_MEMO_LENGTH
+                )));
+            }
+        }
+
+        let metadata_keys = self.metadata.iter().flat_map(|metadata| metadata.keys());
+        let json_keys = self
+            .metadata_json
+            .as_ref()
+            .and_then(|value| value.as_object())
+            .into_iter()
+            .flat_map(|object| object.keys());
+        if let Some(key) = metadata_keys.chain(json_keys).find(|key| is_reserved_metadata_key(key)) {
+            return Err(QweryError::ConfigError(format!(
+                "metadata key {:?} uses the reserved prefix {:?}",
+                key, RESERVED_METADATA_PREFIX
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+/// Whether `key` starts with [`RESERVED_METADATA_PREFIX`], ignoring case
+fn is_reserved_metadata_key(key: &str) -> bool {
+    key.get(..RESERVED_METADATA_PREFIX.len())
+        .is_some_and(|prefix| prefix.eq_ignore_ascii_case(RESERVED_METADATA_PREFIX))
+}
+
+/// Builder for [`PaymentRequest`]
+///
+/// # Example
+///
+/// ```rust
+/// use qwery_sdk::PaymentRequest;
+/// use serde::Serialize;
+///
+/// #[derive(Serialize)]
+/// struct Order {
+///     id: u64,
+///     items: Vec<String>,
+/// }
+///
+/// let request = PaymentRequest::builder()
+///     .amount(12.5)
+///     .token("USDC")
+///     .recipient("merchant_wallet_address")
+///     .metadata(&Order { id: 42, items: vec!["coffee".to_string()] })
+///     .build()
+///     .unwrap();
+///
+/// assert_eq!(request.metadata_json.unwrap()["id"], 42);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct PaymentRequestBuilder {
+    request: PaymentRequest,
+    error: Option<String>,
+}
+
+impl PaymentRequestBuilder {
+    /// Set the amount to pay
+    pub fn amount(&mut self, amount: f64) -> &mut Self {
+        self.request.amount = amount;
+        self
+    }
+
+    /// Set the token to pay with (SOL, USDC, USDT)
+    pub fn token(&mut self, token: impl Into<String>) -> &mut Self {
+        self.request.token = token.into();
+        self
+    }
+
+    /// Set the recipient wallet address
+    pub fn recipient(&mut self, recipient: impl Into<String>) -> &mut Self {
+        self.request.recipient = recipient.into();
+        self
+    }
+
+    /// Attach any serializable value as structured metadata
+    pub fn metadata<T: Serialize>(&mut self, value: &T) -> &mut Self {
+        match serde_json::to_value(value) {
+            Ok(value) => self.request.metadata_json = Some(value),
+            Err(e) => self.error = Some(format!("invalid metadata: {}", e)),
+        }
+        self
+    }
+
+    /// Set the wallet address that will sign the payment
+    pub fn payer(&mut self, payer: impl Into<String>) -> &mut Self {
+        self.request.payer = Some(payer.into());
+        self
+    }
+
+    /// Set the on-chain memo
+    pub fn memo(&mut self, memo: impl Into<String>) -> &mut Self {
+        self.request.memo = Some(memo.into());
+        self
+    }
+
+    /// Set the priority fee in micro-lamports per compute unit
+    pub fn priority_fee_micro_lamports(&mut self, fee: u64) -> &mut Self {
+        self.request.priority_fee_micro_lamports = Some(fee);
+        self
+    }
+
+    /// Set the compute unit limit
+    pub fn compute_unit_limit(&mut self, limit: u32) -> &mut Self {
+        self.request.compute_unit_limit = Some(limit);
+        self
+    }
+
+    /// Set whether the facilitator creates the recipient's token account
+    pub fn create_recipient_ata(&mut self, create: bool) -> &mut Self {
+        self.request.create_recipient_ata = Some(create);
+        self
+    }
+
+    /// Set the account that pays the transaction fees
+    #[cfg(feature = "signing")]
+    pub fn fee_payer(&mut self, fee_payer: Pubkey) -> &mut Self {
+        self.request.fee_payer = Some(fee_payer);
+        self
+    }
+
+    /// Set the blockhash to build the transaction against
+    #[cfg(feature = "signing")]
+    pub fn recent_blockhash(&mut self, blockhash: Hash) -> &mut Self {
+        self.request.recent_blockhash = Some(blockhash);
+        self
+    }
+
+    /// Set the Solana Pay reference key to add to the transfer
+    #[cfg(feature = "signing")]
+    pub fn reference(&mut self, reference: Pubkey) -> &mut Self {
+        self.request.reference = Some(reference);
+        self
+    }
+
+    /// Validate and build the request
+    pub fn build(&self) -> Result<PaymentRequest> {
+        if let Some(ref error) = self.error {
+            return Err(QweryError::ConfigError(error.clone()));
+        }
+        self.request.validate()?;
+        Ok(self.request.clone())
+    }
+}
+
+/// One recipient's share of a split payment
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PaymentSplit {
+    /// Recipient wallet address
+    pub recipient: String,
+    /// Amount this recipient receives
+    pub amount: f64,
+}
+
+impl PaymentSplit {
+    /// Create a split paying `amount` to `recipient`
+    pub fn new(recipient: impl Into<String>, amount: f64) -> Self {
+        Self {
+            recipient: recipient.into(),
+            amount,
+        }
+    }
 }
 
 /// Response from creating a payment
-#[derive(Debug, Clone, Deserialize)]
+///
+/// Serializes back to the facilitator's format, so a created payment can be
+/// stored and settled later:
+///
+/// ```rust
+/// use qwery_sdk::PaymentResponse;
+///
+/// fn store(payment: &PaymentResponse) -> serde_json::Result<String> {
+///     serde_json::to_string(payment)
+/// }
+///
+/// fn load(stored: &str) -> serde_json::Result<PaymentResponse> {
+///     serde_json::from_str(stored)
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct PaymentResponse {
     /// Unique payment ID
     pub payment_id: String,
-    /// Base64 encoded transaction to sign
+    /// Base64 encoded transaction to sign, empty if not included
+    #[serde(default)]
     pub transaction: String,
     /// Amount in token
     pub amount: f64,
@@ -76,6 +1124,579 @@ pub struct PaymentResponse {
     pub status: String,
     /// Expiration timestamp
     pub expires_at: Option<String>,
+    /// Last block height at which the transaction's blockhash is accepted
+    ///
+    /// The transaction can't land after this, whatever `expires_at` says.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_valid_block_height: Option<u64>,
+}
+
+impl PaymentResponse {
+    /// Parse the payment status, falling back to [`PaymentStatus::Unknown`]
+    pub fn payment_status(&self) -> PaymentStatus {
+        PaymentStatus::parse(&self.status)
+    }
+
+    /// Whether `expires_at` has passed according to `clock`
+    ///
+    /// A payment without an expiry, or with one that isn't an RFC 3339
+    /// timestamp, never counts as expired.
+    ///
+    /// ```rust
+    /// use qwery_sdk::clock::SystemClock;
+    /// use qwery_sdk::PaymentResponse;
+    ///
+    /// fn still_payable(payment: &PaymentResponse) -> bool {
+    ///     !payment.is_expired(&SystemClock)
+    /// }
+    /// ```
+    pub fn is_expired(&self, clock: &dyn Clock) -> bool {
+        self.expires_at
+            .as_deref()
+            .and_then(clock::parse_rfc3339)
+            .is_some_and(|expires_at| clock.now() >= expires_at)
+    }
+
+    /// Parse the recipient wallet address
+    ///
+    /// Fails with [`QweryError::SolanaError`] if `recipient` isn't a valid
+    /// address.
+    ///
+    /// ```rust
+    /// use qwery_sdk::PaymentResponse;
+    ///
+    /// let payment: PaymentResponse = serde_json::from_value(serde_json::json!({
+    ///     "payment_id": "pay_123",
+    ///     "transaction": "",
+    ///     "amount": 1.0,
+    ///     "token": "USDC",
+    ///     "recipient": "9xQeWvG816bUx9EPjHmaT23yvVM2ZWbrrpZb9PusVFin",
+    ///     "network": "solana",
+    ///     "status": "pending",
+    /// })).unwrap();
+    ///
+    /// assert_eq!(
+    ///     payment.recipient_pubkey().unwrap().to_string(),
+    ///     "9xQeWvG816bUx9EPjHmaT23yvVM2ZWbrrpZb9PusVFin",
+    /// );
+    /// ```
+    #[cfg(feature = "signing")]
+    pub fn recipient_pubkey(&self) -> Result<Pubkey> {
+        Pubkey::from_str(&self.recipient).map_err(|e| {
+            QweryError::SolanaError(format!("invalid recipient {:?}: {}", self.recipient, e))
+        })
+    }
+
+    /// Serialized message of the payment's transaction, the bytes a signer signs
+    ///
+    /// For signers that only accept raw messages, such as hardware wallets
+    /// in air-gapped setups. Pass the resulting signature to
+    /// [`apply_signature`](PaymentResponse::apply_signature).
+    ///
+    /// ```rust
+    /// use base64::Engine as _;
+    /// use base64::engine::general_purpose::STANDARD as BASE64;
+    /// use qwery_sdk::PaymentResponse;
+    /// use solana_sdk::signature::{Keypair, Signer};
+    /// use solana_sdk::{pubkey::Pubkey, system_instruction, transaction::Transaction};
+    ///
+    /// let payer = Keypair::new();
+    /// let transfer = system_instruction::transfer(&payer.pubkey(), &Pubkey::new_unique(), 1_000);
+    /// let unsigned = Transaction::new_with_payer(&[transfer], Some(&payer.pubkey()));
+    ///
+    /// let payment: PaymentResponse = serde_json::from_value(serde_json::json!({
+    ///     "payment_id": "pay_123",
+    ///     "transaction": BASE64.encode(bincode::serialize(&unsigned).unwrap()),
+    ///     "amount": 0.000001,
+    ///     "token": "SOL",
+    ///     "recipient": "9xQeWvG816bUx9EPjHmaT23yvVM2ZWbrrpZb9PusVFin",
+    ///     "network": "solana",
+    ///     "status": "pending",
+    /// })).unwrap();
+    ///
+    /// // Signed elsewhere, e.g. on a hardware device
+    /// let message = payment.message_bytes().unwrap();
+    /// let signature = payer.sign_message(&message);
+    ///
+    /// let signed = payment.apply_signature(&payer.pubkey(), &signature).unwrap();
+    /// let signed: Transaction = bincode::deserialize(&BASE64.decode(signed).unwrap()).unwrap();
+    /// assert!(signed.verify().is_ok());
+    /// ```
+    #[cfg(feature = "signing")]
+    pub fn message_bytes(&self) -> Result<Vec<u8>> {
+        Ok(self.decode_transaction()?.message_data())
+    }
+
+    /// Add a signature over [`message_bytes`](PaymentResponse::message_bytes)
+    /// and return the transaction base64 encoded, ready to settle
+    ///
+    /// Other signatures already on the transaction are kept. Returns
+    /// [`QweryError::SigningError`] if `pubkey` isn't a required signer or
+    /// `signature` doesn't verify against the message.
+    ///
+    /// Each call starts from the transaction as returned by the facilitator,
+    /// so for several external signers, apply the signatures to the decoded
+    /// transaction yourself.
+    #[cfg(feature = "signing")]
+    pub fn apply_signature(&self, pubkey: &Pubkey, signature: &Signature) -> Result<String> {
+        let mut transaction = self.decode_transaction()?;
+
+        let signers = transaction.message.header.num_required_signatures as usize;
+        let position = transaction.message.account_keys
+            .iter()
+            .take(signers)
+            .position(|key| key == pubkey)
+            .ok_or_else(|| {
+                QweryError::SigningError(format!("{} is not a required signer", pubkey))
+            })?;
+        if !signature.verify(pubkey.as_ref(), &transaction.message_data()) {
+            return Err(QweryError::SigningError(format!(
+                "signature does not match the message for {}",
+                pubkey
+            )));
+        }
+
+        if transaction.signatures.len() < signers {
+            transaction.signatures.resize(signers, Signature::default());
+        }
+        transaction.signatures[position] = *signature;
+        let tx_bytes = bincode::serialize(&transaction)
+            .map_err(|e| QweryError::SolanaError(e.to_string()))?;
+        Ok(BASE64.encode(tx_bytes))
+    }
+
+    /// Decode the base64 encoded transaction to sign
+    #[cfg(feature = "signing")]
+    pub(crate) fn decode_transaction(&self) -> Result<Transaction> {
+        let tx_bytes = decode_base64(&self.transaction)?;
+
+        bincode::deserialize(&tx_bytes).map_err(|e| QweryError::SolanaError(e.to_string()))
+    }
+}
+
+/// Decode base64 from the facilitator, accepting the standard and URL-safe
+/// alphabets with or without padding
+///
+/// Reports the standard alphabet's error if no variant decodes.
+pub(crate) fn decode_base64(value: &str) -> Result<Vec<u8>> {
+    let value = value.trim();
+    BASE64.decode(value).or_else(|error| {
+        [&STANDARD_NO_PAD, &URL_SAFE, &URL_SAFE_NO_PAD]
+            .into_iter()
+            .find_map(|engine| engine.decode(value).ok())
+            .ok_or(QweryError::Base64Error(error))
+    })
+}
+
+/// Whether a recipient can receive a token, from `QweryClient::check_recipient`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecipientCheck {
+    /// The address is a wallet rather than a program, mint or token account
+    pub address_valid: bool,
+    /// The recipient's associated token account exists, always `false` for SOL
+    pub ata_exists: bool,
+    /// The recipient's associated token account exists and is frozen
+    pub token_account_frozen: bool,
+    /// A payment of the token to the recipient can succeed
+    pub can_receive: bool,
+}
+
+/// Result of [`QweryClient::create_payment_idempotent`]
+///
+/// [`QweryClient::create_payment_idempotent`]: crate::QweryClient::create_payment_idempotent
+#[derive(Debug, Clone, PartialEq)]
+pub enum CreateOutcome {
+    /// A new payment was created by this request
+    Created(PaymentResponse),
+    /// An earlier request with the same idempotency key already created it
+    Existing(PaymentResponse),
+}
+
+impl CreateOutcome {
+    /// The payment, whether new or existing
+    pub fn payment(&self) -> &PaymentResponse {
+        match self {
+            CreateOutcome::Created(payment) | CreateOutcome::Existing(payment) => payment,
+        }
+    }
+
+    /// Take the payment, whether new or existing
+    pub fn into_payment(self) -> PaymentResponse {
+        match self {
+            CreateOutcome::Created(payment) | CreateOutcome::Existing(payment) => payment,
+        }
+    }
+
+    /// Whether the payment was created by an earlier request
+    pub fn is_replay(&self) -> bool {
+        matches!(self, CreateOutcome::Existing(_))
+    }
+}
+
+/// Per-item results of a batch call such as [`QweryClient::create_payments`]
+///
+/// Results keep the order of the batch's input, and the indices returned by
+/// [`succeeded`](Self::succeeded) and [`failed`](Self::failed) point back
+/// into it.
+///
+/// # Example
+///
+/// ```rust
+/// use qwery_sdk::{BatchOutcome, QweryError};
+///
+/// let outcome = BatchOutcome::from(vec![
+///     Ok("pay_1"),
+///     Err(QweryError::ConfigError("invalid recipient".to_string())),
+///     Ok("pay_3"),
+/// ]);
+///
+/// assert_eq!(outcome.success_count(), 2);
+/// println!("{} of {} payouts succeeded", outcome.success_count(), outcome.len());
+/// for (index, error) in outcome.failed() {
+///     println!("payout {} failed: {}", index, error);
+/// }
+/// assert_eq!(outcome.failed().map(|(index, _)| index).collect::<Vec<_>>(), [1]);
+/// ```
+///
+/// [`QweryClient::create_payments`]: crate::QweryClient::create_payments
+#[derive(Debug)]
+pub struct BatchOutcome<T> {
+    results: Vec<Result<T>>,
+}
+
+impl<T> BatchOutcome<T> {
+    /// Successful items with their index in the batch
+    pub fn succeeded(&self) -> impl Iterator<Item = (usize, &T)> {
+        self.results
+            .iter()
+            .enumerate()
+            .filter_map(|(index, result)| result.as_ref().ok().map(|value| (index, value)))
+    }
+
+    /// Failed items with their index in the batch
+    pub fn failed(&self) -> impl Iterator<Item = (usize, &QweryError)> {
+        self.results
+            .iter()
+            .enumerate()
+            .filter_map(|(index, result)| result.as_ref().err().map(|error| (index, error)))
+    }
+
+    /// Number of items that succeeded
+    pub fn success_count(&self) -> usize {
+        self.results.iter().filter(|result| result.is_ok()).count()
+    }
+
+    /// Number of items that failed
+    pub fn failure_count(&self) -> usize {
+        self.len() - self.success_count()
+    }
+
+    /// Whether every item succeeded
+    pub fn all_succeeded(&self) -> bool {
+        self.results.iter().all(Result::is_ok)
+    }
+
+    /// Number of items in the batch
+    pub fn len(&self) -> usize {
+        self.results.len()
+    }
+
+    /// Whether the batch was empty
+    pub fn is_empty(&self) -> bool {
+        self.results.is_empty()
+    }
+
+    /// Take the per-item results, in the order of the batch
+    pub fn into_results(self) -> Vec<Result<T>> {
+        self.results
+    }
+}
+
+impl<T> From<Vec<Result<T>>> for BatchOutcome<T> {
+    fn from(results: Vec<Result<T>>) -> Self {
+        Self { results }
+    }
+}
+
+impl<T> IntoIterator for BatchOutcome<T> {
+    type Item = Result<T>;
+    type IntoIter = std::vec::IntoIter<Result<T>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.results.into_iter()
+    }
+}
+
+/// Estimated network cost of a payment, paid by the facilitator
+#[derive(Debug, Clone, Deserialize)]
+pub struct FeeEstimate {
+    /// Base signature fee in lamports
+    pub base_fee_lamports: u64,
+    /// Priority fee in lamports
+    #[serde(default)]
+    pub priority_fee_lamports: u64,
+    /// Facilitator service fee in lamports
+    #[serde(default)]
+    pub facilitator_fee: u64,
+}
+
+/// Current network performance as observed by the facilitator
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct NetworkConditions {
+    /// Recent average slot time, `slot_time_ms` on the wire
+    #[serde(rename = "slot_time_ms", with = "duration_millis")]
+    pub slot_time: Duration,
+    /// Recent time from submission to `confirmed`, `confirmation_latency_ms`
+    /// on the wire
+    #[serde(rename = "confirmation_latency_ms", with = "duration_millis")]
+    pub confirmation_latency: Duration,
+    /// Priority fee, in micro-lamports per compute unit, likely to land promptly
+    #[serde(default)]
+    pub suggested_priority_fee_micro_lamports: u64,
+}
+
+impl NetworkConditions {
+    /// Estimated time from submission until the transaction is finalized
+    ///
+    /// ```rust
+    /// use qwery_sdk::NetworkConditions;
+    /// use std::time::Duration;
+    ///
+    /// let conditions: NetworkConditions = serde_json::from_str(r#"{
+    ///     "slot_time_ms": 400,
+    ///     "confirmation_latency_ms": 1200,
+    ///     "suggested_priority_fee_micro_lamports": 5000
+    /// }"#).unwrap();
+    ///
+    /// assert_eq!(conditions.estimated_time_to_finality(), Duration::from_millis(14_000));
+    /// ```
+    pub fn estimated_time_to_finality(&self) -> Duration {
+        self.confirmation_latency + self.slot_time * FINALIZED_CONFIRMATIONS as u32
+    }
+}
+
+/// State of the facilitator's fee-payer account on the configured network
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct FacilitatorStatus {
+    /// Whether the fee payer can currently pay for settlements
+    pub fee_payer_funded: bool,
+    /// Fee-payer address, if the facilitator exposes it
+    #[serde(default)]
+    pub fee_payer: Option<String>,
+    /// Fee-payer balance in lamports, if the facilitator exposes it
+    #[serde(default)]
+    pub balance_lamports: Option<u64>,
+    /// Settlements the balance is expected to cover, if the facilitator exposes it
+    #[serde(default)]
+    pub estimated_transactions_remaining: Option<u64>,
+}
+
+impl FacilitatorStatus {
+    /// Whether the fee payer is unfunded or expected to cover fewer than
+    /// `min_transactions` settlements
+    ///
+    /// An unknown remaining count only counts as low when the fee payer is
+    /// unfunded.
+    ///
+    /// ```rust
+    /// use qwery_sdk::FacilitatorStatus;
+    ///
+    /// let status: FacilitatorStatus = serde_json::from_str(r#"{
+    ///     "fee_payer_funded": true,
+    ///     "balance_lamports": 2500000,
+    ///     "estimated_transactions_remaining": 500
+    /// }"#).unwrap();
+    ///
+    /// assert!(!status.is_low(100));
+    /// assert!(status.is_low(1_000));
+    /// ```
+    pub fn is_low(&self, min_transactions: u64) -> bool {
+        !self.fee_payer_funded
+            || self
+                .estimated_transactions_remaining
+                .is_some_and(|remaining| remaining < min_transactions)
+    }
+}
+
+/// Lifecycle status of a payment
+///
+/// Serializes, displays and parses as the lowercase name, e.g. `settled`.
+/// These strings are stable across SDK versions, so they can be stored, for
+/// example as a database enum, and read back.
+///
+/// ```rust
+/// use qwery_sdk::PaymentStatus;
+///
+/// let stored = PaymentStatus::Settled.to_string();
+/// assert_eq!(stored, "settled");
+/// assert_eq!(stored.parse::<PaymentStatus>().unwrap(), PaymentStatus::Settled);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PaymentStatus {
+    /// Created and waiting for a signed transaction
+    Pending,
+    /// Signed transaction submitted to the network
+    Submitted,
+    /// Transaction landed on-chain
+    Settled,
+    /// Settlement failed
+    Failed,
+    /// Payment expired before it was settled
+    Expired,
+    /// Status not known to this version of the SDK
+    #[serde(other)]
+    Unknown,
+}
+
+impl PaymentStatus {
+    /// Get the status name as stored and sent by the facilitator
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PaymentStatus::Pending => "pending",
+            PaymentStatus::Submitted => "submitted",
+            PaymentStatus::Settled => "settled",
+            PaymentStatus::Failed => "failed",
+            PaymentStatus::Expired => "expired",
+            PaymentStatus::Unknown => "unknown",
+        }
+    }
+
+    /// Parse a status string, falling back to [`PaymentStatus::Unknown`]
+    pub(crate) fn parse(status: &str) -> PaymentStatus {
+        let lowercase = status.to_ascii_lowercase();
+        let deserializer: StrDeserializer<'_, serde::de::value::Error> =
+            lowercase.as_str().into_deserializer();
+        PaymentStatus::deserialize(deserializer).unwrap_or(PaymentStatus::Unknown)
+    }
+
+    /// Whether the payment can no longer change status
+    pub fn is_terminal(&self) -> bool {
+        matches!(
+            self,
+            PaymentStatus::Settled | PaymentStatus::Failed | PaymentStatus::Expired
+        )
+    }
+}
+
+impl fmt::Display for PaymentStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for PaymentStatus {
+    type Err = QweryError;
+
+    /// Parse a status name, rejecting names this SDK doesn't know
+    ///
+    /// Use this for stored values; responses from the facilitator are parsed
+    /// leniently into [`PaymentStatus::Unknown`] instead.
+    fn from_str(s: &str) -> Result<Self> {
+        match PaymentStatus::parse(s) {
+            PaymentStatus::Unknown if !s.eq_ignore_ascii_case("unknown") => {
+                Err(QweryError::ConfigError(format!("unknown payment status: {}", s)))
+            }
+            status => Ok(status),
+        }
+    }
+}
+
+/// Criteria for listing payments with [`QweryClient::list_payments`]
+///
+/// Unset fields don't restrict the results.
+///
+/// [`QweryClient::list_payments`]: crate::QweryClient::list_payments
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct PaymentFilter {
+    /// Only payments with this status
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<PaymentStatus>,
+    /// Only payments in this token
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token: Option<Token>,
+    /// Only payments to this recipient address
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub recipient: Option<String>,
+    /// Only payments from this payer address
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub payer: Option<String>,
+    /// Most payments per page, up to the facilitator's own limit
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u32>,
+    /// Resume after the page that returned this `next_cursor`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<String>,
+}
+
+/// One page of payments from [`QweryClient::list_payments`]
+///
+/// [`QweryClient::list_payments`]: crate::QweryClient::list_payments
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct PaymentPage {
+    /// Payments on this page
+    #[serde(default)]
+    pub payments: Vec<PaymentResponse>,
+    /// Cursor for the next page, `None` on the last page
+    pub next_cursor: Option<String>,
+}
+
+/// File format of a payment export from `QweryClient::export_payments`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    /// Comma-separated values with a header row
+    Csv,
+    /// One JSON payment object per line
+    Ndjson,
+}
+
+impl ExportFormat {
+    /// Get the format name as sent to the facilitator
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Ndjson => "ndjson",
+        }
+    }
+
+    /// MIME type of an export in this format
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "text/csv",
+            ExportFormat::Ndjson => "application/x-ndjson",
+        }
+    }
+}
+
+/// Status change for a single payment
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct PaymentStatusUpdate {
+    /// Payment the update is for
+    pub payment_id: String,
+    /// New payment status
+    pub status: PaymentStatus,
+}
+
+/// Payment event as delivered by webhooks or [`QweryClient::poll_events`]
+///
+/// [`QweryClient::poll_events`]: crate::QweryClient::poll_events
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct WebhookEvent {
+    /// Unique, ordered event ID, usable as a `since` cursor
+    pub id: String,
+    /// Event type, e.g. `payment.settled`
+    #[serde(rename = "type")]
+    pub event_type: String,
+    /// Payment the event is about
+    pub payment_id: String,
+    /// Payment status after the event
+    pub status: PaymentStatus,
+    /// When the event happened
+    pub created_at: Option<String>,
+    /// Additional event data
+    #[serde(default)]
+    pub data: serde_json::Value,
 }
 
 /// Request to settle a payment
@@ -87,8 +1708,72 @@ pub struct SettleRequest {
     pub signed_transaction: String,
 }
 
+/// A payment's transaction, signed but not yet settled
+///
+/// Returned by `QweryClient::sign_payment`. Converts into a [`SettleRequest`]
+/// to settle it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignedPayment {
+    /// Payment the transaction belongs to
+    pub payment_id: String,
+    /// Base64 encoded signed transaction
+    pub signed_transaction_base64: String,
+    /// Transaction signature, base58 encoded
+    pub signature: String,
+}
+
+impl From<SignedPayment> for SettleRequest {
+    fn from(signed: SignedPayment) -> Self {
+        Self {
+            payment_id: signed.payment_id,
+            signed_transaction: signed.signed_transaction_base64,
+        }
+    }
+}
+
+/// How the facilitator submits a signed transaction to the cluster
+///
+/// Passed to `settle_payment_with_options`; the fields map to Solana's
+/// `sendTransaction` configuration.
+///
+/// ```rust
+/// use qwery_sdk::{CommitmentLevel, SettleOptions};
+///
+/// let options = SettleOptions {
+///     skip_preflight: true,
+///     max_retries: Some(0),
+///     ..Default::default()
+/// };
+/// assert_eq!(options.preflight_commitment, CommitmentLevel::Finalized);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SettleOptions {
+    /// Send the transaction without simulating it first
+    pub skip_preflight: bool,
+    /// Commitment the preflight simulation runs against
+    pub preflight_commitment: CommitmentLevel,
+    /// How many times the RPC node retries sending the transaction
+    ///
+    /// `None` leaves it to the node's default.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_retries: Option<u32>,
+}
+
+impl Default for SettleOptions {
+    fn default() -> Self {
+        Self {
+            skip_preflight: false,
+            preflight_commitment: CommitmentLevel::Finalized,
+            max_retries: None,
+        }
+    }
+}
+
 /// Response from settling a payment
-#[derive(Debug, Clone, Deserialize)]
+///
+/// Compared without `submit_latency`, which differs between otherwise
+/// identical settlements.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SettleResponse {
     /// Whether settlement was successful
     pub success: bool,
@@ -98,8 +1783,28 @@ pub struct SettleResponse {
     pub status: String,
     /// Error message if failed
     pub error: Option<String>,
+    /// Program logs of the transaction, if the facilitator included them
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub logs: Vec<String>,
+    /// Time from submitting the signed transaction to the facilitator's reply
+    ///
+    /// Measured by the client; `None` unless returned by a settle call.
+    #[serde(skip)]
+    pub submit_latency: Option<Duration>,
 }
 
+impl PartialEq for SettleResponse {
+    fn eq(&self, other: &Self) -> bool {
+        self.success == other.success
+            && self.signature == other.signature
+            && self.status == other.status
+            && self.error == other.error
+            && self.logs == other.logs
+    }
+}
+
+impl Eq for SettleResponse {}
+
 /// Request to verify a payment
 #[derive(Debug, Clone, Serialize)]
 pub struct VerifyRequest {
@@ -107,17 +1812,168 @@ pub struct VerifyRequest {
     pub signature: String,
     /// Network to verify on
     pub network: String,
+    /// Commitment the transaction must reach to count as verified
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub commitment: Option<CommitmentLevel>,
+}
+
+/// How settled a transaction must be before it counts as verified
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CommitmentLevel {
+    /// Processed by a node but may still be dropped
+    Processed,
+    /// Voted on by a supermajority of the cluster
+    Confirmed,
+    /// Rooted, can no longer be rolled back
+    Finalized,
 }
 
 /// Response from verifying a payment
-#[derive(Debug, Clone, Deserialize)]
+///
+/// Compared without `time_to_finality`, which differs between otherwise
+/// identical results.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct VerifyResponse {
     /// Whether the payment is verified
     pub verified: bool,
     /// Payment status
     pub status: String,
-    /// Confirmation count
+    /// Confirmation count, reported whether or not the payment is verified yet
+    ///
+    /// `None` means the transaction hasn't been seen on chain, `Some(0)` that
+    /// it landed but has no confirmations yet.
     pub confirmations: Option<u64>,
+    /// Transaction signature that was verified
+    pub signature: Option<String>,
+    /// Commitment level the result reflects, if reported
+    #[serde(default)]
+    pub commitment: Option<CommitmentLevel>,
+    /// Network the transaction was verified on, if reported
+    #[serde(default)]
+    pub network: Option<String>,
+    /// Time from the first poll until the payment was verified
+    ///
+    /// Measured by the client; only set by `wait_for_confirmation`.
+    #[serde(skip)]
+    pub time_to_finality: Option<Duration>,
+}
+
+impl PartialEq for VerifyResponse {
+    fn eq(&self, other: &Self) -> bool {
+        self.verified == other.verified
+            && self.status == other.status
+            && self.confirmations == other.confirmations
+            && self.signature == other.signature
+            && self.commitment == other.commitment
+            && self.network == other.network
+    }
+}
+
+impl Eq for VerifyResponse {}
+
+/// Outcome of verifying a transaction, from [`VerifyResponse::verify_status`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum VerifyStatus {
+    /// Not seen on chain, either not landed yet or an unknown signature
+    NotFound,
+    /// Landed but not yet at the required commitment
+    Pending,
+    /// Verified at the required commitment
+    Confirmed,
+    /// Landed but failed, or doesn't match the payment; will never verify
+    Failed,
+}
+
+/// Confirmations after which a transaction is treated as finalized
+pub const FINALIZED_CONFIRMATIONS: u64 = 32;
+
+impl VerifyResponse {
+    /// Classify the result, telling a transaction worth waiting for from one
+    /// that will never verify
+    pub fn verify_status(&self) -> VerifyStatus {
+        if self.verified {
+            return VerifyStatus::Confirmed;
+        }
+        match self.status.to_ascii_lowercase().as_str() {
+            "failed" | "mismatch" | "error" | "expired" | "rejected" => VerifyStatus::Failed,
+            "not_found" | "notfound" | "unknown" => VerifyStatus::NotFound,
+            _ if self.confirmations.is_none() => VerifyStatus::NotFound,
+            _ => VerifyStatus::Pending,
+        }
+    }
+
+    /// Whether the transaction is finalized and can no longer be rolled back
+    ///
+    /// ```rust
+    /// use qwery_sdk::VerifyResponse;
+    ///
+    /// let response: VerifyResponse = serde_json::from_str(
+    ///     r#"{ "verified": true, "status": "confirmed", "confirmations": 40 }"#,
+    /// ).unwrap();
+    /// assert!(response.is_finalized());
+    /// ```
+    pub fn is_finalized(&self) -> bool {
+        self.status.eq_ignore_ascii_case("finalized")
+            || self
+                .confirmations
+                .is_some_and(|c| c >= FINALIZED_CONFIRMATIONS)
+    }
+
+    /// Fraction of `target` confirmations reached, from 0.0 to 1.0
+    ///
+    /// A transaction that hasn't been seen yet reports 0.0.
+    ///
+    /// ```rust
+    /// use qwery_sdk::{VerifyResponse, FINALIZED_CONFIRMATIONS};
+    ///
+    /// let response: VerifyResponse = serde_json::from_str(
+    ///     r#"{ "verified": false, "status": "submitted", "confirmations": 8 }"#,
+    /// ).unwrap();
+    /// assert_eq!(response.confirmation_progress(FINALIZED_CONFIRMATIONS), 0.25);
+    /// assert_eq!(response.confirmation_progress(4), 1.0);
+    /// ```
+    pub fn confirmation_progress(&self, target: u64) -> f32 {
+        if target == 0 {
+            return 1.0;
+        }
+        let confirmations = self.confirmations.unwrap_or(0);
+        (confirmations as f64 / target as f64).min(1.0) as f32
+    }
+}
+
+/// Transfer a transaction must contain to pass [`QweryClient::verify_onchain`]
+///
+/// [`QweryClient::verify_onchain`]: crate::QweryClient::verify_onchain
+#[cfg(feature = "signing")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExpectedTransfer {
+    /// Wallet that must receive the funds (the owner, for SPL tokens)
+    pub recipient: Pubkey,
+    /// Token that must be transferred
+    pub token: Token,
+    /// Minimum amount received, in base units
+    pub amount: u64,
+}
+
+#[cfg(feature = "signing")]
+impl ExpectedTransfer {
+    /// The transfer `payment` was created for
+    pub fn from_payment(payment: &PaymentResponse) -> Result<Self> {
+        let token = Token::from_str(&payment.token)?;
+        Ok(Self {
+            recipient: payment.recipient_pubkey()?,
+            token,
+            amount: token.to_base_units(payment.amount),
+        })
+    }
+}
+
+/// Token balance of a wallet
+#[derive(Debug, Clone, Deserialize)]
+pub struct BalanceResponse {
+    /// Balance in base units
+    pub balance: u64,
 }
 
 /// Health status of the facilitator
@@ -128,5 +1984,335 @@ pub struct HealthResponse {
     /// API version
     pub version: String,
     /// Network statuses
+    #[serde(default)]
     pub networks: HashMap<String, String>,
 }
+
+impl HealthResponse {
+    /// Whether the facilitator and every network it serves are fully operational
+    ///
+    /// ```rust
+    /// use qwery_sdk::HealthResponse;
+    ///
+    /// let health: HealthResponse = serde_json::from_str(r#"{
+    ///     "status": "ok",
+    ///     "version": "1.0.0",
+    ///     "networks": { "solana": "ok", "solana-devnet": "down" }
+    /// }"#).unwrap();
+    ///
+    /// assert!(!health.is_healthy());
+    /// assert!(health.is_degraded());
+    /// ```
+    pub fn is_healthy(&self) -> bool {
+        is_operational(&self.status) && self.networks.values().all(|s| is_operational(s))
+    }
+
+    /// Whether the facilitator is up but not fully operational
+    pub fn is_degraded(&self) -> bool {
+        !self.is_healthy()
+            && (is_operational(&self.status)
+                || self.status.eq_ignore_ascii_case("degraded")
+                || self.networks.values().any(|s| is_operational(s)))
+    }
+
+    /// The status blocking payments on `network`, or `None` if it's operational
+    ///
+    /// A network the facilitator doesn't report on follows the overall status.
+    ///
+    /// ```rust
+    /// use qwery_sdk::{HealthResponse, Network};
+    ///
+    /// let health: HealthResponse = serde_json::from_str(r#"{
+    ///     "status": "ok",
+    ///     "version": "1.0.0",
+    ///     "networks": { "solana": "ok", "solana-devnet": "down" }
+    /// }"#).unwrap();
+    ///
+    /// assert_eq!(health.network_outage(Network::Mainnet), None);
+    /// assert_eq!(health.network_outage(Network::Devnet), Some("down"));
+    /// ```
+    pub fn network_outage(&self, network: Network) -> Option<&str> {
+        if !is_operational(&self.status) {
+            return Some(&self.status);
+        }
+        self.networks
+            .get(network.as_str())
+            .map(String::as_str)
+            .filter(|status| !is_operational(status))
+    }
+}
+
+/// Whether a facilitator status string means "operational"
+fn is_operational(status: &str) -> bool {
+    ["ok", "healthy", "operational", "up"]
+        .iter()
+        .any(|s| status.eq_ignore_ascii_case(s))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn network_names_parse_case_insensitively() {
+        let network: Network = "DEVNET".parse().unwrap();
+        assert_eq!(network, Network::Devnet);
+        assert_eq!(network.to_string(), "Devnet");
+        assert_eq!(network.as_str(), "solana-devnet");
+
+        assert_eq!("Mainnet".parse::<Network>().unwrap(), Network::Mainnet);
+        assert!(matches!("testnet".parse::<Network>(), Err(QweryError::ConfigError(_))));
+    }
+
+    #[test]
+    fn settle_and_verify_responses_compare_without_timings() {
+        let settled: SettleResponse =
+            serde_json::from_str(r#"{"success":true,"signature":"sig","status":"settled","error":null}"#)
+                .unwrap();
+        let mut timed = settled.clone();
+        timed.submit_latency = Some(Duration::from_millis(250));
+        assert_eq!(timed, settled);
+        timed.status = "failed".to_string();
+        assert_ne!(timed, settled);
+
+        let verified: VerifyResponse = serde_json::from_str(
+            r#"{"verified":true,"status":"confirmed","confirmations":32,"signature":"sig"}"#,
+        )
+        .unwrap();
+        let mut timed = verified.clone();
+        timed.time_to_finality = Some(Duration::from_secs(13));
+        assert_eq!(timed, verified);
+        timed.confirmations = Some(1);
+        assert_ne!(timed, verified);
+    }
+
+    #[test]
+    fn format_amount_trims_zeros_but_keeps_cents_for_stablecoins() {
+        assert_eq!(Token::Sol.format_amount(100_000_000), "0.1 SOL");
+        assert_eq!(Token::Sol.format_amount(2_000_000_000), "2 SOL");
+        assert_eq!(Token::Sol.format_amount(1), "0.000000001 SOL");
+        assert_eq!(Token::Usdc.format_amount(1_500_000), "1.50 USDC");
+        assert_eq!(Token::Usdc.format_amount(0), "0.00 USDC");
+        assert_eq!(Token::Usdt.format_amount(1_234_567), "1.234567 USDT");
+    }
+
+    #[test]
+    fn parse_amount_is_exact_and_checks_the_symbol() {
+        assert_eq!(Token::Sol.parse_amount("0.1").unwrap(), 100_000_000);
+        assert_eq!(Token::Usdc.parse_amount("1.50 USDC").unwrap(), 1_500_000);
+        assert_eq!(Token::Usdc.parse_amount("1.5 usdc").unwrap(), 1_500_000);
+        assert_eq!(Token::Usdc.parse_amount("3").unwrap(), 3_000_000);
+        assert_eq!(Token::Usdc.parse_amount(".25").unwrap(), 250_000);
+
+        for input in ["0.0000001", "1 SOL", "", ".", "-1", "1e6", "1.5 USDC extra"] {
+            assert!(Token::Usdc.parse_amount(input).is_err(), "{:?} was accepted", input);
+        }
+        assert!(Token::Sol.parse_amount("18446744073.709551616").is_err());
+    }
+
+    #[test]
+    fn payment_response_round_trips_through_storage() {
+        let payment: PaymentResponse = serde_json::from_value(serde_json::json!({
+            "payment_id": "pay_123",
+            "transaction": "AQAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA==",
+            "amount": 1.5,
+            "token": "USDC",
+            "recipient": "9xQeWvG816bUx9EPjHmaT23yvVM2ZWbrrpZb9PusVFin",
+            "network": "solana",
+            "status": "pending",
+            "expires_at": "2025-01-01T00:00:00Z",
+        }))
+        .unwrap();
+
+        let stored = serde_json::to_string(&payment).unwrap();
+        let restored: PaymentResponse = serde_json::from_str(&stored).unwrap();
+        assert_eq!(restored, payment);
+        assert_eq!(restored.transaction, payment.transaction);
+
+        let settled: SettleResponse =
+            serde_json::from_str(r#"{"success":true,"signature":"sig","status":"settled","error":null}"#)
+                .unwrap();
+        let restored: SettleResponse = serde_json::from_str(&serde_json::to_string(&settled).unwrap()).unwrap();
+        assert_eq!(restored, settled);
+    }
+
+    #[cfg(feature = "signing")]
+    #[test]
+    fn mint_addresses_differ_by_network_and_native_sol_has_none() {
+        let usdc = Token::Usdc.mint_address(Network::Mainnet).unwrap();
+        assert_eq!(
+            Token::Usdc.mint_address(Network::Devnet).unwrap().to_string(),
+            "4zMMC9srt5Ri5X14GAgXhaHii3GnPAEERYPJgZJDncDU"
+        );
+        assert_ne!(Token::Usdc.mint_address(Network::Devnet), Some(usdc));
+        assert_eq!(Token::Sol.mint_address(Network::Mainnet), None);
+        assert_eq!(Token::Usdt.mint_address(Network::Devnet), None);
+    }
+
+    #[test]
+    fn rounding_policy_only_touches_amounts_with_excess_decimals() {
+        assert_eq!(RoundingPolicy::Reject.apply(Token::Sol, 0.25).unwrap(), 0.25);
+        assert_eq!(RoundingPolicy::Reject.apply(Token::Usdc, 0.123456).unwrap(), 0.123456);
+        assert!(matches!(
+            RoundingPolicy::Reject.apply(Token::Usdc, 0.1234567),
+            Err(QweryError::ConfigError(_))
+        ));
+
+        assert_eq!(RoundingPolicy::Round.apply(Token::Usdc, 0.1234567).unwrap(), 0.123457);
+        assert_eq!(RoundingPolicy::Round.apply(Token::Sol, 1.0000000004).unwrap(), 1.0);
+
+        // Left for the amount checks to reject
+        assert_eq!(RoundingPolicy::Reject.apply(Token::Usdc, -0.1234567).unwrap(), -0.1234567);
+        assert!(RoundingPolicy::Round.apply(Token::Usdc, f64::NAN).unwrap().is_nan());
+    }
+
+    #[test]
+    fn responses_ignore_unknown_fields_and_default_missing_ones() {
+        let payment: PaymentResponse = serde_json::from_str(
+            r#"{
+                "payment_id": "pay_123",
+                "transaction": "",
+                "amount": 1.5,
+                "token": "USDC",
+                "recipient": "recipient_address",
+                "network": "solana",
+                "status": "pending",
+                "created_by": "api",
+                "risk": { "score": 0.1 }
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(payment.expires_at, None);
+
+        let settled: SettleResponse =
+            serde_json::from_str(r#"{ "success": true, "status": "settled", "slot": 250000000 }"#).unwrap();
+        assert_eq!(settled.signature, None);
+
+        let verified: VerifyResponse = serde_json::from_str(
+            r#"{ "verified": true, "status": "finalized", "block_time": 1700000000 }"#,
+        )
+        .unwrap();
+        assert_eq!(verified.confirmations, None);
+
+        let fee: FeeEstimate = serde_json::from_str(r#"{ "base_fee_lamports": 5000 }"#).unwrap();
+        assert_eq!(fee.priority_fee_lamports, 0);
+
+        let health: HealthResponse =
+            serde_json::from_str(r#"{ "status": "ok", "version": "1.2", "uptime_secs": 86400 }"#).unwrap();
+        assert!(health.networks.is_empty());
+    }
+
+    #[test]
+    fn payments_expire_by_the_given_clock() {
+        use crate::clock::MockClock;
+
+        let mut payment: PaymentResponse = serde_json::from_value(serde_json::json!({
+            "payment_id": "pay_123",
+            "amount": 1.0,
+            "token": "USDC",
+            "recipient": "9xQeWvG816bUx9EPjHmaT23yvVM2ZWbrrpZb9PusVFin",
+            "network": "solana",
+            "status": "pending",
+            "expires_at": "2025-01-01T00:00:00Z",
+        }))
+        .unwrap();
+
+        // One second before the expiry
+        let clock = MockClock::new(Duration::from_secs(1_735_689_599));
+        assert!(!payment.is_expired(&clock));
+        clock.advance(Duration::from_secs(1));
+        assert!(payment.is_expired(&clock));
+        assert!(payment.is_expired(&SystemClock));
+
+        for expires_at in [None, Some("next tuesday".to_string())] {
+            payment.expires_at = expires_at;
+            assert!(!payment.is_expired(&clock));
+        }
+    }
+
+    #[test]
+    fn token_strings_round_trip_through_display_parse_and_serde() {
+        for token in [Token::Sol, Token::Usdc, Token::Usdt] {
+            let stored = token.to_string();
+            assert_eq!(stored, token.as_str());
+            assert_eq!(stored.parse::<Token>().unwrap(), token);
+            assert_eq!(stored.to_lowercase().parse::<Token>().unwrap(), token);
+            assert_eq!(serde_json::to_value(token).unwrap(), stored.as_str());
+            assert_eq!(serde_json::from_value::<Token>(stored.into()).unwrap(), token);
+        }
+        assert!("BONK".parse::<Token>().is_err());
+    }
+
+    #[test]
+    fn payment_status_strings_round_trip_through_display_parse_and_serde() {
+        let all = [
+            PaymentStatus::Pending,
+            PaymentStatus::Submitted,
+            PaymentStatus::Settled,
+            PaymentStatus::Failed,
+            PaymentStatus::Expired,
+            PaymentStatus::Unknown,
+        ];
+        for status in all {
+            let stored = status.to_string();
+            assert_eq!(stored, status.as_str());
+            assert_eq!(stored.parse::<PaymentStatus>().unwrap(), status);
+            assert_eq!(serde_json::to_value(status).unwrap(), stored.as_str());
+            assert_eq!(serde_json::from_value::<PaymentStatus>(stored.into()).unwrap(), status);
+        }
+        assert!("refunded".parse::<PaymentStatus>().is_err());
+    }
+
+    #[test]
+    fn retries_stop_at_max_retries_or_before_passing_the_deadline() {
+        let delay = Duration::from_secs(1);
+
+        let policy = RetryPolicy::default();
+        assert!(policy.should_retry(policy.max_retries - 1, Duration::from_secs(3600), delay));
+        assert!(!policy.should_retry(policy.max_retries, Duration::ZERO, delay));
+
+        let policy = RetryPolicy {
+            total_deadline: Some(Duration::from_secs(5)),
+            ..Default::default()
+        };
+        assert!(policy.should_retry(0, Duration::from_secs(3), delay));
+        assert!(policy.should_retry(0, Duration::from_secs(4), delay));
+        assert!(!policy.should_retry(0, Duration::from_millis(4500), delay));
+    }
+
+    #[test]
+    fn verify_status_tells_pending_from_never_verifying() {
+        let status = |json: &str| serde_json::from_str::<VerifyResponse>(json).unwrap().verify_status();
+
+        assert_eq!(
+            status(r#"{ "verified": false, "status": "not_found", "confirmations": null }"#),
+            VerifyStatus::NotFound
+        );
+        assert_eq!(
+            status(r#"{ "verified": false, "status": "processed", "confirmations": null }"#),
+            VerifyStatus::NotFound
+        );
+        assert_eq!(
+            status(r#"{ "verified": false, "status": "processed", "confirmations": 0 }"#),
+            VerifyStatus::Pending
+        );
+        assert_eq!(
+            status(r#"{ "verified": true, "status": "confirmed", "confirmations": 40 }"#),
+            VerifyStatus::Confirmed
+        );
+        assert_eq!(
+            status(r#"{ "verified": false, "status": "Mismatch", "confirmations": 3 }"#),
+            VerifyStatus::Failed
+        );
+    }
+
+    #[test]
+    fn minimum_amounts_cover_mainnet_fees_only() {
+        assert_eq!(Token::Sol.minimum_amount(Network::Mainnet), 5_000);
+        assert_eq!(Token::Usdc.minimum_amount(Network::Mainnet), 1_000);
+        assert_eq!(Token::Usdt.minimum_amount(Network::Mainnet), 1_000);
+        for token in [Token::Sol, Token::Usdc, Token::Usdt] {
+            assert_eq!(token.minimum_amount(Network::Devnet), 1);
+        }
+    }
+}
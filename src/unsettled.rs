@@ -0,0 +1,34 @@
+//! Development aid that reports payments created but never settled
+
+use std::collections::BTreeSet;
+use std::sync::Mutex;
+
+/// IDs of payments created through a client and not yet settled by it
+///
+/// Shared by every clone of the client, and warns about what is left when
+/// the last clone is dropped.
+#[derive(Debug, Default)]
+pub(crate) struct UnsettledPayments(Mutex<BTreeSet<String>>);
+
+impl UnsettledPayments {
+    pub(crate) fn created(&self, payment_id: &str) {
+        self.0.lock().unwrap().insert(payment_id.to_string());
+    }
+
+    pub(crate) fn settled(&self, payment_id: &str) {
+        self.0.lock().unwrap().remove(payment_id);
+    }
+}
+
+impl Drop for UnsettledPayments {
+    fn drop(&mut self) {
+        let payment_ids = self.0.get_mut().unwrap_or_else(|e| e.into_inner());
+        if !payment_ids.is_empty() {
+            tracing::warn!(
+                count = payment_ids.len(),
+                ?payment_ids,
+                "client dropped with payments that were created but never settled"
+            );
+        }
+    }
+}
@@ -0,0 +1,137 @@
+//! Payment URIs for wallet deep links
+
+#[cfg(feature = "qr")]
+use crate::error::{QweryError, Result};
+use crate::types::{Network, PaymentResponse, Token};
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use std::str::FromStr;
+
+/// Characters left unescaped in query values (RFC 3986 unreserved)
+const QUERY_VALUE: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
+
+/// URI scheme to target with a payment link
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UriScheme {
+    /// Solana Pay `solana:` transfer request, understood by most wallets
+    Solana,
+    /// Qwery deep link `qwery://pay`
+    Qwery,
+}
+
+impl PaymentResponse {
+    /// Build a wallet deep link for this payment
+    ///
+    /// The link carries the recipient, amount, token mint (for SPL tokens)
+    /// and payment ID.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use qwery_sdk::PaymentResponse;
+    /// use qwery_sdk::uri::UriScheme;
+    ///
+    /// let payment: PaymentResponse = serde_json::from_str(r#"{
+    ///     "payment_id": "pay_123",
+    ///     "transaction": "",
+    ///     "amount": 1.5,
+    ///     "token": "USDC",
+    ///     "recipient": "9xQeWvG816bUx9EPjHmaT23yvVM2ZWbrrpZb9PusVFin",
+    ///     "network": "solana",
+    ///     "status": "pending",
+    ///     "expires_at": null
+    /// }"#).unwrap();
+    ///
+    /// assert_eq!(
+    ///     payment.to_payment_url(UriScheme::Solana),
+    ///     "solana:9xQeWvG816bUx9EPjHmaT23yvVM2ZWbrrpZb9PusVFin?amount=1.5\
+    ///      &spl-token=EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v&payment_id=pay_123",
+    /// );
+    /// ```
+    pub fn to_payment_url(&self, scheme: UriScheme) -> String {
+        let mut params = vec![("amount", self.amount.to_string())];
+
+        let network = Network::from_wire(&self.network);
+        let mint = Token::from_str(&self.token)
+            .ok()
+            .zip(network)
+            .and_then(|(token, network)| token.mint_str(network));
+
+        let base = match scheme {
+            UriScheme::Solana => {
+                if let Some(mint) = mint {
+                    params.push(("spl-token", mint.to_string()));
+                }
+                format!("solana:{}", encode(&self.recipient))
+            }
+            UriScheme::Qwery => {
+                params.insert(0, ("recipient", self.recipient.clone()));
+                params.push(("token", self.token.clone()));
+                if let Some(mint) = mint {
+                    params.push(("mint", mint.to_string()));
+                }
+                params.push(("network", self.network.clone()));
+                "qwery://pay".to_string()
+            }
+        };
+        params.push(("payment_id", self.payment_id.clone()));
+
+        let query = params
+            .iter()
+            .map(|(key, value)| format!("{}={}", key, encode(value)))
+            .collect::<Vec<_>>()
+            .join("&");
+
+        format!("{}?{}", base, query)
+    }
+}
+
+#[cfg(feature = "qr")]
+impl PaymentResponse {
+    /// Render the Solana Pay link for this payment as an SVG QR code
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # fn example(payment: qwery_sdk::PaymentResponse) -> qwery_sdk::error::Result<()> {
+    /// let svg = payment.qr_svg()?;
+    /// std::fs::write("payment.svg", svg).unwrap();
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn qr_svg(&self) -> Result<String> {
+        let code = self.qr_code()?;
+        Ok(code
+            .render::<qrcode::render::svg::Color>()
+            .min_dimensions(256, 256)
+            .build())
+    }
+
+    /// Render the Solana Pay link for this payment as a PNG QR code
+    pub fn qr_png_bytes(&self) -> Result<Vec<u8>> {
+        let image = self
+            .qr_code()?
+            .render::<image::Luma<u8>>()
+            .min_dimensions(256, 256)
+            .build();
+
+        let mut bytes = Vec::new();
+        image
+            .write_to(&mut std::io::Cursor::new(&mut bytes), image::ImageFormat::Png)
+            .map_err(|e| QweryError::QrError(e.to_string()))?;
+        Ok(bytes)
+    }
+
+    fn qr_code(&self) -> Result<qrcode::QrCode> {
+        qrcode::QrCode::new(self.to_payment_url(UriScheme::Solana))
+            .map_err(|e| QweryError::QrError(e.to_string()))
+    }
+}
+
+/// Percent-encode everything but RFC 3986 unreserved characters
+pub(crate) fn encode(value: &str) -> String {
+    utf8_percent_encode(value, QUERY_VALUE).to_string()
+}
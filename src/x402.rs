@@ -0,0 +1,493 @@
+//! Helpers for the x402 HTTP payment protocol
+
+use crate::error::{QweryError, Result};
+use crate::types::{Network, PaymentRequest, SettleResponse, Token};
+use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// Header carrying a base64 encoded challenge when the body is empty
+const PAYMENT_REQUIRED_HEADER: &str = "x-payment-required";
+/// Header naming the facilitator the resource server expects
+const FACILITATOR_HEADER: &str = "x-payment-facilitator";
+
+/// Header carrying a [`PaymentProof`] on the request that is retried after paying
+pub const PAYMENT_HEADER: &str = "x-payment";
+
+/// x402 protocol version written into payment proofs
+const X402_VERSION: u32 = 1;
+
+/// Payment requirements extracted from an HTTP 402 challenge
+#[derive(Debug, Clone, PartialEq)]
+pub struct PaymentRequirements {
+    /// Payment scheme (e.g. "exact")
+    pub scheme: String,
+    /// Network the payment must be made on
+    pub network: String,
+    /// Amount required in base units
+    pub amount: u64,
+    /// Asset to pay with (token symbol or mint address)
+    pub token: String,
+    /// Recipient wallet address
+    pub recipient: String,
+    /// Facilitator the resource server expects, if advertised
+    pub facilitator_url: Option<String>,
+    /// Resource being paid for
+    pub resource: Option<String>,
+    /// Human readable description of the resource
+    pub description: Option<String>,
+    /// Maximum time the server waits for payment, in seconds
+    pub max_timeout_seconds: Option<u64>,
+}
+
+impl PaymentRequirements {
+    /// Convert the requirements into a request for [`QweryClient::create_payment`]
+    ///
+    /// The asset may be a token symbol or the token's mint address on the
+    /// challenge's network.
+    ///
+    /// [`QweryClient::create_payment`]: crate::QweryClient::create_payment
+    pub fn to_payment_request(&self) -> Result<PaymentRequest> {
+        let token = Token::from_str(&self.token).or_else(|error| {
+            Network::from_wire(&self.network)
+                .and_then(|network| {
+                    [Token::Sol, Token::Usdc, Token::Usdt]
+                        .into_iter()
+                        .find(|token| token.mint_str(network) == Some(self.token.as_str()))
+                })
+                .ok_or(error)
+        })?;
+
+        Ok(PaymentRequest {
+            amount: token.from_base_units(self.amount),
+            token: token.as_str().to_string(),
+            recipient: self.recipient.clone(),
+            ..Default::default()
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct PaymentRequiredBody {
+    #[serde(default)]
+    accepts: Vec<AcceptedPayment>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AcceptedPayment {
+    scheme: String,
+    network: String,
+    max_amount_required: String,
+    pay_to: String,
+    asset: String,
+    resource: Option<String>,
+    description: Option<String>,
+    max_timeout_seconds: Option<u64>,
+    extra: Option<serde_json::Value>,
+}
+
+/// Parse the payment requirements from an HTTP 402 response
+///
+/// The challenge is read from the JSON body, or from the base64 encoded
+/// `X-Payment-Required` header when the body is empty. The first Solana
+/// entry in `accepts` is used.
+///
+/// # Example
+///
+/// ```rust
+/// use qwery_sdk::x402::parse_payment_required;
+/// use reqwest::header::HeaderMap;
+///
+/// let body = br#"{
+///     "x402Version": 1,
+///     "accepts": [{
+///         "scheme": "exact",
+///         "network": "solana",
+///         "maxAmountRequired": "10000",
+///         "payTo": "recipient_address",
+///         "asset": "USDC"
+///     }]
+/// }"#;
+///
+/// let requirements = parse_payment_required(&HeaderMap::new(), body).unwrap();
+/// let request = requirements.to_payment_request().unwrap();
+/// assert_eq!(request.amount, 0.01);
+/// ```
+pub fn parse_payment_required(headers: &HeaderMap, body: &[u8]) -> Result<PaymentRequirements> {
+    let parsed: PaymentRequiredBody = if body.iter().all(u8::is_ascii_whitespace) {
+        let encoded = headers
+            .get(PAYMENT_REQUIRED_HEADER)
+            .ok_or_else(|| QweryError::X402Error("empty body and no challenge header".to_string()))?
+            .to_str()
+            .map_err(|e| QweryError::X402Error(e.to_string()))?;
+        let decoded = BASE64.decode(encoded.trim())?;
+        serde_json::from_slice(&decoded).map_err(|e| QweryError::X402Error(e.to_string()))?
+    } else {
+        serde_json::from_slice(body).map_err(|e| QweryError::X402Error(e.to_string()))?
+    };
+
+    let accepted = parsed
+        .accepts
+        .into_iter()
+        .find(|a| a.network.starts_with("solana"))
+        .ok_or_else(|| QweryError::X402Error("no Solana payment option offered".to_string()))?;
+
+    let amount = accepted.max_amount_required.parse::<u64>().map_err(|e| {
+        QweryError::X402Error(format!(
+            "invalid maxAmountRequired {:?}: {}",
+            accepted.max_amount_required, e
+        ))
+    })?;
+
+    let facilitator_url = accepted
+        .extra
+        .as_ref()
+        .and_then(|extra| extra.get("facilitatorUrl"))
+        .and_then(|url| url.as_str())
+        .map(str::to_string)
+        .or_else(|| {
+            headers
+                .get(FACILITATOR_HEADER)
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_string)
+        });
+
+    Ok(PaymentRequirements {
+        scheme: accepted.scheme,
+        network: accepted.network,
+        amount,
+        token: accepted.asset,
+        recipient: accepted.pay_to,
+        facilitator_url,
+        resource: accepted.resource,
+        description: accepted.description,
+        max_timeout_seconds: accepted.max_timeout_seconds,
+    })
+}
+
+/// Proof that an x402 payment was settled, sent back to the resource server
+///
+/// Encoded as base64 JSON in the `X-Payment` header of the retried request.
+///
+/// # Example
+///
+/// ```rust
+/// use qwery_sdk::x402::{PaymentProof, PAYMENT_HEADER};
+///
+/// let proof = PaymentProof::new("exact", "solana", "pay_123", "transaction_signature");
+/// let (name, value) = proof.to_header().unwrap();
+/// assert_eq!(name.as_str(), PAYMENT_HEADER);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PaymentProof {
+    /// Payment scheme from the challenge (e.g. "exact")
+    pub scheme: String,
+    /// Network the payment was made on
+    pub network: String,
+    /// Qwery payment ID
+    pub payment_id: String,
+    /// Signature of the settled transaction
+    pub signature: String,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ProofWire {
+    x402_version: u32,
+    scheme: String,
+    network: String,
+    payload: ProofPayload,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ProofPayload {
+    payment_id: String,
+    signature: String,
+}
+
+impl PaymentProof {
+    /// Create a proof for a settled payment
+    pub fn new(
+        scheme: impl Into<String>,
+        network: impl Into<String>,
+        payment_id: impl Into<String>,
+        signature: impl Into<String>,
+    ) -> Self {
+        Self {
+            scheme: scheme.into(),
+            network: network.into(),
+            payment_id: payment_id.into(),
+            signature: signature.into(),
+        }
+    }
+
+    /// Create the proof for paying `requirements` with a settled payment
+    ///
+    /// Returns [`QweryError::X402Error`] if `settlement` did not succeed or
+    /// carries no signature.
+    pub fn from_settlement(
+        requirements: &PaymentRequirements,
+        payment_id: &str,
+        settlement: &SettleResponse,
+    ) -> Result<Self> {
+        let signature = match settlement.signature {
+            Some(ref signature) if settlement.success => signature,
+            _ => {
+                return Err(QweryError::X402Error(format!(
+                    "payment {} has no settled signature to prove",
+                    payment_id
+                )))
+            }
+        };
+
+        Ok(Self::new(
+            requirements.scheme.as_str(),
+            requirements.network.as_str(),
+            payment_id,
+            signature.as_str(),
+        ))
+    }
+
+    /// Encode the proof as the base64 JSON value of the `X-Payment` header
+    pub fn encode(&self) -> String {
+        let wire = ProofWire {
+            x402_version: X402_VERSION,
+            scheme: self.scheme.clone(),
+            network: self.network.clone(),
+            payload: ProofPayload {
+                payment_id: self.payment_id.clone(),
+                signature: self.signature.clone(),
+            },
+        };
+        BASE64.encode(serde_json::to_string(&wire).expect("proof serializes to JSON"))
+    }
+
+    /// Decode a proof from an `X-Payment` header value
+    pub fn decode(value: &str) -> Result<Self> {
+        let decoded = BASE64.decode(value.trim())?;
+        let wire: ProofWire =
+            serde_json::from_slice(&decoded).map_err(|e| QweryError::X402Error(e.to_string()))?;
+
+        Ok(Self {
+            scheme: wire.scheme,
+            network: wire.network,
+            payment_id: wire.payload.payment_id,
+            signature: wire.payload.signature,
+        })
+    }
+
+    /// The `X-Payment` header to attach to the retried request
+    pub fn to_header(&self) -> Result<(HeaderName, HeaderValue)> {
+        let value = HeaderValue::from_str(&self.encode())
+            .map_err(|e| QweryError::X402Error(e.to_string()))?;
+        Ok((HeaderName::from_static(PAYMENT_HEADER), value))
+    }
+}
+
+#[cfg(feature = "middleware")]
+pub use middleware::PaymentMiddleware;
+
+#[cfg(feature = "middleware")]
+mod middleware {
+    use super::{parse_payment_required, PaymentProof};
+    use crate::client::QweryClient;
+    use crate::error::QweryError;
+    use crate::types::{Network, Token};
+    use reqwest::{Request, Response, StatusCode};
+    use reqwest_middleware::{Middleware, Next};
+    use solana_sdk::signature::Keypair;
+    use std::collections::HashMap;
+    use std::str::FromStr;
+    use std::sync::Arc;
+    use task_local_extensions::Extensions;
+
+    /// `reqwest_middleware` middleware that pays HTTP 402 challenges automatically
+    ///
+    /// On a 402 response the challenge is parsed, paid through the Qwery
+    /// facilitator with the configured keypair, and the original request is
+    /// replayed once with an `X-Payment` proof header. Only tokens with a
+    /// configured limit are paid, and never above that limit. Challenges for
+    /// a network other than the client's are refused.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use qwery_sdk::{QweryClient, Network, Token};
+    /// use qwery_sdk::x402::PaymentMiddleware;
+    /// use solana_sdk::signature::Keypair;
+    ///
+    /// let client = QweryClient::new(Network::Mainnet).unwrap();
+    /// let payments = PaymentMiddleware::new(client, Keypair::new())
+    ///     .with_max_amount(Token::Usdc, 1_000_000);
+    ///
+    /// let http = reqwest_middleware::ClientBuilder::new(reqwest::Client::new())
+    ///     .with(payments)
+    ///     .build();
+    /// ```
+    pub struct PaymentMiddleware {
+        client: QweryClient,
+        keypair: Arc<Keypair>,
+        max_amounts: HashMap<Token, u64>,
+    }
+
+    impl PaymentMiddleware {
+        /// Create a middleware that pays with `keypair` through `client`
+        pub fn new(client: QweryClient, keypair: Keypair) -> Self {
+            Self {
+                client,
+                keypair: Arc::new(keypair),
+                max_amounts: HashMap::new(),
+            }
+        }
+
+        /// Allow automatic payments in `token` up to `max_base_units` each
+        pub fn with_max_amount(mut self, token: Token, max_base_units: u64) -> Self {
+            self.max_amounts.insert(token, max_base_units);
+            self
+        }
+
+        async fn pay(&self, response: Response) -> Result<PaymentProof, QweryError> {
+            let headers = response.headers().clone();
+            let body = response.bytes().await?;
+            let requirements = parse_payment_required(&headers, &body)?;
+
+            let network = self.client.config().network;
+            if Network::from_wire(&requirements.network) != Some(network) {
+                return Err(QweryError::X402Error(format!(
+                    "challenge is for network {:?}, client is configured for {}",
+                    requirements.network, network
+                )));
+            }
+
+            let request = requirements.to_payment_request()?;
+            let token = Token::from_str(&request.token)?;
+            let limit = self.max_amounts.get(&token).copied().unwrap_or(0);
+            if requirements.amount > limit {
+                return Err(QweryError::X402Error(format!(
+                    "payment of {} {} base units exceeds auto-pay limit of {}",
+                    requirements.amount, token, limit
+                )));
+            }
+
+            let payment = self
+                .client
+                .create_payment(request)
+                .await?;
+            let settled = self.client.sign_and_settle(&payment, &self.keypair).await?;
+
+            PaymentProof::from_settlement(&requirements, &payment.payment_id, &settled)
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl Middleware for PaymentMiddleware {
+        async fn handle(
+            &self,
+            req: Request,
+            extensions: &mut Extensions,
+            next: Next<'_>,
+        ) -> reqwest_middleware::Result<Response> {
+            // Requests with streaming bodies can't be replayed, so pass them through
+            let Some(mut retry) = req.try_clone() else {
+                return next.run(req, extensions).await;
+            };
+
+            let response = next.clone().run(req, extensions).await?;
+            if response.status() != StatusCode::PAYMENT_REQUIRED {
+                return Ok(response);
+            }
+
+            let (name, value) = self
+                .pay(response)
+                .await
+                .and_then(|proof| proof.to_header())
+                .map_err(reqwest_middleware::Error::middleware)?;
+            retry.headers_mut().insert(name, value);
+
+            next.run(retry, extensions).await
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CHALLENGE: &str = r#"{
+        "x402Version": 1,
+        "accepts": [
+            { "scheme": "exact", "network": "base", "maxAmountRequired": "1", "payTo": "0xabc", "asset": "USDC" },
+            {
+                "scheme": "exact",
+                "network": "solana-devnet",
+                "maxAmountRequired": "2500000",
+                "payTo": "recipient_address",
+                "asset": "4zMMC9srt5Ri5X14GAgXhaHii3GnPAEERYPJgZJDncDU",
+                "extra": { "facilitatorUrl": "https://facilitator.example" }
+            }
+        ]
+    }"#;
+
+    #[test]
+    fn picks_the_solana_option_and_maps_its_mint_to_a_token() {
+        let requirements = parse_payment_required(&HeaderMap::new(), CHALLENGE.as_bytes()).unwrap();
+        assert_eq!(requirements.network, "solana-devnet");
+        assert_eq!(requirements.amount, 2_500_000);
+        assert_eq!(requirements.facilitator_url.as_deref(), Some("https://facilitator.example"));
+
+        let request = requirements.to_payment_request().unwrap();
+        assert_eq!(request.token, "USDC");
+        assert_eq!(request.amount, 2.5);
+        assert_eq!(request.recipient, "recipient_address");
+    }
+
+    #[test]
+    fn reads_the_challenge_header_when_the_body_is_empty() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            PAYMENT_REQUIRED_HEADER,
+            HeaderValue::from_str(&BASE64.encode(CHALLENGE)).unwrap(),
+        );
+
+        let requirements = parse_payment_required(&headers, b"  ").unwrap();
+        assert_eq!(requirements.amount, 2_500_000);
+        assert!(matches!(
+            parse_payment_required(&HeaderMap::new(), b""),
+            Err(QweryError::X402Error(_))
+        ));
+    }
+
+    #[test]
+    fn mint_addresses_only_match_on_the_challenge_network() {
+        // The devnet USDC mint means nothing on mainnet
+        let mainnet = CHALLENGE.replace("solana-devnet", "solana");
+        let requirements = parse_payment_required(&HeaderMap::new(), mainnet.as_bytes()).unwrap();
+        assert!(matches!(requirements.to_payment_request(), Err(QweryError::ConfigError(_))));
+    }
+
+    #[test]
+    fn payment_proof_round_trips_through_its_header() {
+        let proof = PaymentProof::new("exact", "solana", "pay_123", "transaction_signature");
+        let (_, value) = proof.to_header().unwrap();
+        assert_eq!(PaymentProof::decode(value.to_str().unwrap()).unwrap(), proof);
+
+        let wire: serde_json::Value = serde_json::from_slice(&BASE64.decode(proof.encode()).unwrap()).unwrap();
+        assert_eq!(wire["x402Version"], 1);
+        assert_eq!(wire["payload"]["paymentId"], "pay_123");
+    }
+
+    #[test]
+    fn payment_proof_needs_a_settled_signature() {
+        let requirements = parse_payment_required(&HeaderMap::new(), CHALLENGE.as_bytes()).unwrap();
+        let failed: SettleResponse =
+            serde_json::from_str(r#"{"success":false,"signature":"sig","status":"failed","error":null}"#)
+                .unwrap();
+        assert!(matches!(
+            PaymentProof::from_settlement(&requirements, "pay_123", &failed),
+            Err(QweryError::X402Error(_))
+        ));
+    }
+}